@@ -0,0 +1,295 @@
+//! Watching an on-chain deposit address for incoming payments.
+//!
+//! ```ignore
+//! let address = client.create_onchain_address(request).await?.data;
+//! let mut deposits = Box::pin(
+//!     client
+//!         .watch_onchain_address(&address)
+//!         .with_expected_amount(50_000)
+//!         .into_stream(),
+//! );
+//! while let Some(update) = deposits.next().await {
+//!     let update = update?;
+//!     if update.is_fully_paid() == Some(true) {
+//!         break;
+//!     }
+//! }
+//! ```
+
+use crate::client::BitvoraClient;
+use crate::errors::APIError;
+use crate::models::{CreateOnChainAddressData, GetDepositData};
+use crate::poller::DEFAULT_INTERVAL;
+use futures_util::Stream;
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+
+/// Where a deposit stands, from its status.
+///
+/// Bitvora reports a deposit's status rather than its number of
+/// confirmations, so this is as fine-grained as progress gets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DepositState {
+    /// Seen on chain but not confirmed yet.
+    Pending,
+    /// Confirmed and credited.
+    Confirmed,
+    /// Failed or expired; it won't be credited.
+    Failed,
+    /// A status the SDK doesn't know.
+    Other,
+}
+
+impl DepositState {
+    pub fn from_status(status: &str) -> Self {
+        match status {
+            "pending" | "unconfirmed" => DepositState::Pending,
+            "completed" | "confirmed" | "settled" => DepositState::Confirmed,
+            "failed" | "expired" | "cancelled" => DepositState::Failed,
+            _ => DepositState::Other,
+        }
+    }
+
+    /// Whether the deposit won't change any more.
+    pub fn is_final(self) -> bool {
+        matches!(self, DepositState::Confirmed | DepositState::Failed)
+    }
+}
+
+/// A new deposit to the watched address, or a change in one's status.
+#[derive(Debug, Clone)]
+pub struct AddressDeposit {
+    pub deposit: GetDepositData,
+    pub state: DepositState,
+    /// Sats of all confirmed deposits to the address so far.
+    pub confirmed_sats: u64,
+    /// Sats of deposits to the address still waiting for confirmation.
+    pub pending_sats: u64,
+    /// Sats still missing from the expected amount, if one was set.
+    pub remaining_sats: Option<u64>,
+}
+
+impl AddressDeposit {
+    /// Whether confirmed deposits cover the expected amount. `None` without
+    /// an expected amount.
+    pub fn is_fully_paid(&self) -> Option<bool> {
+        self.remaining_sats.map(|remaining| remaining == 0)
+    }
+}
+
+/// Reports every deposit to one on-chain address, including partial and
+/// repeated payments, when they are first seen and whenever their status
+/// changes.
+#[derive(Debug)]
+pub struct AddressWatcher {
+    client: BitvoraClient,
+    address_id: String,
+    interval: Duration,
+    expected_sats: Option<u64>,
+}
+
+impl AddressWatcher {
+    pub fn new(client: BitvoraClient, address: &CreateOnChainAddressData) -> Self {
+        Self {
+            client,
+            address_id: address.id.clone(),
+            interval: DEFAULT_INTERVAL,
+            expected_sats: None,
+        }
+    }
+
+    pub fn address_id(&self) -> &str {
+        &self.address_id
+    }
+
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Amount the address is meant to receive, to track partial payments.
+    pub fn with_expected_amount(mut self, sats: u64) -> Self {
+        self.expected_sats = Some(sats);
+        self
+    }
+
+    /// Yields an update per new deposit and per status change, until dropped.
+    /// Deposits made before the watcher started are reported first. Errors
+    /// are yielded and polling carries on.
+    pub fn into_stream(self) -> impl Stream<Item = Result<AddressDeposit, APIError>> + Send {
+        let state = WatchState {
+            watcher: self,
+            seen: HashMap::new(),
+            queue: VecDeque::new(),
+            polled: false,
+        };
+        futures_util::stream::unfold(state, |mut state| async move {
+            let item = state.next().await;
+            Some((item, state))
+        })
+    }
+}
+
+struct WatchState {
+    watcher: AddressWatcher,
+    // Last reported version of every deposit, by id.
+    seen: HashMap<String, GetDepositData>,
+    // New deposits and status changes still to report.
+    queue: VecDeque<GetDepositData>,
+    polled: bool,
+}
+
+impl WatchState {
+    async fn next(&mut self) -> Result<AddressDeposit, APIError> {
+        loop {
+            if let Some(deposit) = self.queue.pop_front() {
+                self.seen.insert(deposit.id.clone(), deposit.clone());
+                return Ok(self.update(deposit));
+            }
+
+            if self.polled {
+                tokio::time::sleep(self.watcher.interval).await;
+            }
+            self.polled = true;
+            let deposits = self
+                .watcher
+                .client
+                .list_onchain_address_deposits(&self.watcher.address_id)
+                .await?
+                .data;
+            self.queue = deposits
+                .into_iter()
+                .filter(|deposit| {
+                    self.seen
+                        .get(&deposit.id)
+                        .is_none_or(|seen| seen.status != deposit.status)
+                })
+                .collect();
+        }
+    }
+
+    fn update(&self, deposit: GetDepositData) -> AddressDeposit {
+        let (mut confirmed_sats, mut pending_sats) = (0, 0);
+        for seen in self.seen.values() {
+            match DepositState::from_status(&seen.status) {
+                DepositState::Confirmed => confirmed_sats += seen.amount_sats,
+                DepositState::Pending => pending_sats += seen.amount_sats,
+                DepositState::Failed | DepositState::Other => {}
+            }
+        }
+        AddressDeposit {
+            state: DepositState::from_status(&deposit.status),
+            deposit,
+            confirmed_sats,
+            pending_sats,
+            remaining_sats: self
+                .watcher
+                .expected_sats
+                .map(|expected| expected.saturating_sub(confirmed_sats)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{self, with, Reply, Requests};
+    use futures_util::StreamExt;
+    use serde_json::json;
+    use std::sync::{Arc, Mutex};
+
+    type Deposits = Arc<Mutex<Vec<(&'static str, u64, &'static str)>>>;
+
+    /// Serves the deposits of address `a1` from `(id, amount, status)` rows.
+    fn client(deposits: Deposits) -> (BitvoraClient, Requests) {
+        test_support::client(move |request| {
+            assert_eq!(request.path(), "/v1/bitcoin/deposit/on-chain/a1/deposits");
+            let rows: Vec<_> = deposits
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|(id, amount, status)| {
+                    with(
+                        test_support::deposit(id, status),
+                        json!({
+                            "recipient": "tb1qwatched",
+                            "amount_sats": amount,
+                            "rail_type": "onchain",
+                            "chain_tx_id": format!("tx_{id}"),
+                        }),
+                    )
+                })
+                .collect();
+            Reply::ok(json!(rows))
+        })
+    }
+
+    #[tokio::test]
+    async fn test_partial_payments_and_confirmations() {
+        let deposits = Deposits::default();
+        deposits.lock().unwrap().push(("d1", 20_000, "pending"));
+        let address = CreateOnChainAddressData {
+            id: "a1".to_string(),
+            address: "tb1qwatched".to_string(),
+            metadata: None,
+            created_at: "2024-01-01T00:00:00Z".to_string(),
+        };
+        let (client, requests) = client(deposits.clone());
+        let mut updates = Box::pin(
+            client
+                .watch_onchain_address(&address)
+                .with_interval(Duration::from_millis(1))
+                .with_expected_amount(50_000)
+                .into_stream(),
+        );
+
+        let update = updates.next().await.unwrap().unwrap();
+        assert_eq!(update.deposit.id, "d1");
+        assert_eq!(update.state, DepositState::Pending);
+        assert_eq!((update.confirmed_sats, update.pending_sats), (0, 20_000));
+        assert_eq!(update.is_fully_paid(), Some(false));
+
+        *deposits.lock().unwrap() = vec![
+            ("d1", 20_000, "completed"),
+            ("d2", 30_000, "completed"),
+            ("d3", 10_000, "failed"),
+        ];
+        let update = updates.next().await.unwrap().unwrap();
+        assert_eq!(update.deposit.chain_tx_id.as_deref(), Some("tx_d1"));
+        assert_eq!(update.state, DepositState::Confirmed);
+        assert_eq!(update.remaining_sats, Some(30_000));
+
+        let update = updates.next().await.unwrap().unwrap();
+        assert_eq!(update.deposit.id, "d2");
+        assert_eq!((update.confirmed_sats, update.pending_sats), (50_000, 0));
+        assert_eq!(update.is_fully_paid(), Some(true));
+
+        // A failed deposit is final and counts as neither confirmed nor
+        // pending.
+        let update = updates.next().await.unwrap().unwrap();
+        assert_eq!(update.deposit.id, "d3");
+        assert_eq!(update.state, DepositState::Failed);
+        assert!(update.state.is_final());
+        assert_eq!((update.confirmed_sats, update.pending_sats), (50_000, 0));
+        assert!(requests
+            .all()
+            .iter()
+            .all(|request| request.path() == "/v1/bitcoin/deposit/on-chain/a1/deposits"));
+    }
+
+    #[test]
+    fn test_deposit_states() {
+        assert_eq!(
+            DepositState::from_status("unconfirmed"),
+            DepositState::Pending
+        );
+        assert_eq!(
+            DepositState::from_status("confirmed"),
+            DepositState::Confirmed
+        );
+        assert_eq!(DepositState::from_status("expired"), DepositState::Failed);
+        assert_eq!(DepositState::from_status("reorged"), DepositState::Other);
+        assert!(!DepositState::Pending.is_final());
+        assert!(!DepositState::Other.is_final());
+    }
+}
@@ -0,0 +1,184 @@
+//! Posts signed sample Bitvora webhooks to a local URL.
+//!
+//! ```text
+//! bitvora-webhook-sim --url http://localhost:3000/webhooks --secret whsec_... send withdrawal.completed
+//! bitvora-webhook-sim --url ... sequence --preset onchain-deposit --delay-ms 500
+//! bitvora-webhook-sim --url ... send deposit.lightning.completed --duplicate 2
+//! bitvora-webhook-sim --url ... send withdrawal.failed --bad-signature
+//! ```
+
+use bitvora::webhooks::simulator::{sample_payload, Fault, Simulator};
+use bitvora::webhooks::EVENT_TYPES;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::process::ExitCode;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[derive(Parser)]
+#[command(
+    name = "bitvora-webhook-sim",
+    about = "Send signed sample Bitvora webhooks"
+)]
+struct Cli {
+    /// Webhook URL to deliver to.
+    #[arg(long, default_value = "http://localhost:3000/webhooks")]
+    url: String,
+
+    /// Secret to sign deliveries with.
+    #[arg(long, env = "BITVORA_WEBHOOK_SECRET", hide_env_values = true)]
+    secret: Option<String>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// List the event types that can be simulated.
+    Events,
+    /// Deliver a single event.
+    Send {
+        event: String,
+        #[command(flatten)]
+        delivery: DeliveryArgs,
+    },
+    /// Deliver several events in order, about the same object.
+    Sequence {
+        /// Event types to send, e.g. `deposit.onchain.pending deposit.onchain.completed`.
+        events: Vec<String>,
+        /// A common sequence instead of listing events.
+        #[arg(long, conflicts_with = "events")]
+        preset: Option<Preset>,
+        /// Pause between events.
+        #[arg(long, default_value_t = 0)]
+        delay_ms: u64,
+        #[command(flatten)]
+        delivery: DeliveryArgs,
+    },
+}
+
+#[derive(Args)]
+struct DeliveryArgs {
+    /// Id of the deposit or withdrawal; random if omitted.
+    #[arg(long)]
+    id: Option<String>,
+    /// Sign with the wrong secret.
+    #[arg(long, conflicts_with = "stale")]
+    bad_signature: bool,
+    /// Send a timestamp outside the default tolerance.
+    #[arg(long)]
+    stale: bool,
+    /// Resend each delivery unchanged this many extra times.
+    #[arg(long, default_value_t = 0)]
+    duplicate: u32,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Preset {
+    LightningDeposit,
+    OnchainDeposit,
+    Withdrawal,
+    FailedWithdrawal,
+}
+
+impl Preset {
+    fn events(self) -> &'static [&'static str] {
+        match self {
+            Preset::LightningDeposit => &["deposit.lightning.completed"],
+            Preset::OnchainDeposit => &["deposit.onchain.pending", "deposit.onchain.completed"],
+            Preset::Withdrawal => &["withdrawal.pending", "withdrawal.completed"],
+            Preset::FailedWithdrawal => &["withdrawal.pending", "withdrawal.failed"],
+        }
+    }
+}
+
+impl DeliveryArgs {
+    fn fault(&self) -> Fault {
+        if self.bad_signature {
+            Fault::BadSignature
+        } else if self.stale {
+            Fault::StaleTimestamp
+        } else {
+            Fault::None
+        }
+    }
+
+    fn id(&self) -> String {
+        self.id.clone().unwrap_or_else(|| {
+            let nanos = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_nanos();
+            format!("sim_{:x}", nanos)
+        })
+    }
+}
+
+async fn deliver(
+    simulator: &Simulator,
+    event: &str,
+    id: &str,
+    delivery: &DeliveryArgs,
+) -> Result<(), bitvora::APIError> {
+    let body = sample_payload(event, id);
+    let headers = simulator.headers(&body, delivery.fault());
+
+    for attempt in 0..=delivery.duplicate {
+        let status = simulator.post(headers.clone(), &body).await?;
+        let label = if attempt == 0 { "" } else { " (duplicate)" };
+        println!("{} {}{} -> {}", event, id, label, status);
+    }
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    if cli.secret.is_none() && !matches!(cli.command, Command::Events) {
+        eprintln!("A webhook secret is required: pass --secret or set BITVORA_WEBHOOK_SECRET");
+        return ExitCode::FAILURE;
+    }
+    let simulator = Simulator::new(&cli.url, cli.secret.as_deref().unwrap_or_default());
+
+    let result = match &cli.command {
+        Command::Events => {
+            for event in EVENT_TYPES {
+                println!("{}", event);
+            }
+            Ok(())
+        }
+        Command::Send { event, delivery } => {
+            deliver(&simulator, event, &delivery.id(), delivery).await
+        }
+        Command::Sequence {
+            events,
+            preset,
+            delay_ms,
+            delivery,
+        } => {
+            let events: Vec<&str> = match preset {
+                Some(preset) => preset.events().to_vec(),
+                None => events.iter().map(String::as_str).collect(),
+            };
+            let id = delivery.id();
+            let mut result = Ok(());
+            for (index, event) in events.iter().enumerate() {
+                if index > 0 && *delay_ms > 0 {
+                    tokio::time::sleep(Duration::from_millis(*delay_ms)).await;
+                }
+                result = deliver(&simulator, event, &id, delivery).await;
+                if result.is_err() {
+                    break;
+                }
+            }
+            result
+        }
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("Delivery to {} failed: {}", cli.url, err);
+            ExitCode::FAILURE
+        }
+    }
+}
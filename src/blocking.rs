@@ -0,0 +1,863 @@
+//! A synchronous client, for code that isn't async.
+//!
+//! [`BitvoraClient`] has the same methods as the async
+//! [`crate::BitvoraClient`] and returns the same models, but blocks the
+//! calling thread until the response arrives:
+//!
+//! ```ignore
+//! let client = bitvora::blocking::BitvoraClient::new(url, &api_key)?;
+//! let balance = client.get_balance()?;
+//! ```
+//!
+//! Requests run on a small runtime owned by the client, so callers don't
+//! need one of their own. Don't call it from async code: blocking inside a
+//! tokio runtime panics. Use the async client there instead. Dropping the
+//! client there is fine; its runtime is shut down without waiting.
+//!
+//! Pending withdrawals and invoices, address watchers, the hydrator and the
+//! read-only client have blocking counterparts here too. The poller, the
+//! webhook receivers and `with_events` on pending handles are left out: they
+//! are fed by or feed into async code, which a blocking caller doesn't run.
+
+use crate::address::AddressDeposit;
+use crate::errors::{APIError, ScopeError, WaitError};
+use crate::hydrate::HydratedTransaction;
+use crate::invoice::InvoiceStatus;
+use crate::models::*;
+use crate::secret::SecretProvider;
+use crate::transactions::TransactionStream;
+use futures_util::{Stream, StreamExt};
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::runtime::{Builder, Runtime};
+use tower::{BoxError, Service};
+
+/// Blocking counterpart of [`crate::BitvoraClient`]. Clones share the
+/// connection pool and the runtime.
+#[derive(Clone)]
+pub struct BitvoraClient {
+    inner: crate::BitvoraClient,
+    runtime: Arc<OwnedRuntime>,
+}
+
+impl BitvoraClient {
+    /// Fails if the client's runtime can't be started.
+    pub fn new(base_url: &str, api_key: &str) -> std::io::Result<Self> {
+        Self::from_async(crate::BitvoraClient::new(base_url, api_key))
+    }
+
+    /// See [`crate::BitvoraClient::from_secret_provider`].
+    pub fn from_secret_provider(
+        base_url: &str,
+        provider: impl SecretProvider + 'static,
+    ) -> std::io::Result<Self> {
+        Self::from_async(crate::BitvoraClient::from_secret_provider(
+            base_url, provider,
+        ))
+    }
+
+    /// See [`crate::BitvoraClient::with_transport`].
+    pub fn with_transport<S>(base_url: &str, api_key: &str, transport: S) -> std::io::Result<Self>
+    where
+        S: Service<reqwest::Request, Response = reqwest::Response> + Clone + Send + Sync + 'static,
+        S::Error: Into<BoxError>,
+        S::Future: Send + 'static,
+    {
+        Self::from_async(crate::BitvoraClient::with_transport(
+            base_url, api_key, transport,
+        ))
+    }
+
+    /// Wraps an already configured async client.
+    pub fn from_async(inner: crate::BitvoraClient) -> std::io::Result<Self> {
+        let runtime = Builder::new_current_thread().enable_all().build()?;
+        Ok(Self {
+            inner,
+            runtime: Arc::new(OwnedRuntime(Some(runtime))),
+        })
+    }
+
+    pub fn with_secret_provider(mut self, provider: impl SecretProvider + 'static) -> Self {
+        self.inner = self.inner.with_secret_provider(provider);
+        self
+    }
+
+    /// The async client behind this one.
+    pub fn as_async(&self) -> &crate::BitvoraClient {
+        &self.inner
+    }
+
+    /// See [`crate::BitvoraClient::into_read_only`].
+    pub fn into_read_only(self) -> ReadOnlyClient {
+        ReadOnlyClient {
+            inner: self.inner.into_read_only(),
+            runtime: self.runtime,
+        }
+    }
+
+    pub fn withdraw(&self, request: WithdrawRequest) -> Result<WithdrawResponse, APIError> {
+        self.runtime.block_on(self.inner.withdraw(request))
+    }
+
+    /// Like [`BitvoraClient::withdraw`], returning a handle that can wait for
+    /// the withdrawal to settle.
+    pub fn withdraw_and_track(
+        &self,
+        request: WithdrawRequest,
+    ) -> Result<PendingWithdrawal, APIError> {
+        let inner = self
+            .runtime
+            .block_on(self.inner.withdraw_and_track(request))?;
+        Ok(PendingWithdrawal {
+            inner,
+            runtime: self.runtime.clone(),
+        })
+    }
+
+    /// A handle to an existing withdrawal.
+    pub fn pending_withdrawal(&self, id: &str) -> PendingWithdrawal {
+        PendingWithdrawal {
+            inner: self.inner.pending_withdrawal(id),
+            runtime: self.runtime.clone(),
+        }
+    }
+
+    pub fn estimate_withdrawal(
+        &self,
+        request: EstimateWithdrawalRequest,
+    ) -> Result<EstimateWithdrawalResponse, APIError> {
+        self.runtime
+            .block_on(self.inner.estimate_withdrawal(request))
+    }
+
+    pub fn create_lightning_invoice(
+        &self,
+        request: CreateLightningInvoiceRequest,
+    ) -> Result<CreateLightningInvoiceResponse, APIError> {
+        self.runtime
+            .block_on(self.inner.create_lightning_invoice(request))
+    }
+
+    /// Like [`BitvoraClient::create_lightning_invoice`], returning a handle
+    /// that can wait for the invoice to be paid.
+    pub fn create_lightning_invoice_and_track(
+        &self,
+        request: CreateLightningInvoiceRequest,
+    ) -> Result<PendingInvoice, APIError> {
+        let inner = self
+            .runtime
+            .block_on(self.inner.create_lightning_invoice_and_track(request))?;
+        Ok(PendingInvoice {
+            inner,
+            runtime: self.runtime.clone(),
+        })
+    }
+
+    pub fn get_lightning_invoice(
+        &self,
+        id: &str,
+    ) -> Result<CreateLightningInvoiceResponse, APIError> {
+        self.runtime.block_on(self.inner.get_lightning_invoice(id))
+    }
+
+    pub fn get_lightning_invoice_by_hash(
+        &self,
+        r_hash: &str,
+    ) -> Result<CreateLightningInvoiceResponse, APIError> {
+        self.runtime
+            .block_on(self.inner.get_lightning_invoice_by_hash(r_hash))
+    }
+
+    pub fn list_lightning_invoices(
+        &self,
+        query: LightningInvoiceQuery,
+    ) -> Result<ListLightningInvoicesResponse, APIError> {
+        self.runtime
+            .block_on(self.inner.list_lightning_invoices(query))
+    }
+
+    /// A handle to an existing lightning invoice.
+    pub fn pending_invoice(&self, id: &str) -> PendingInvoice {
+        PendingInvoice {
+            inner: self.inner.pending_invoice(id),
+            runtime: self.runtime.clone(),
+        }
+    }
+
+    pub fn create_lightning_address(
+        &self,
+        request: CreateLightningAddressRequest,
+    ) -> Result<CreateLightningAddressResponse, APIError> {
+        self.runtime
+            .block_on(self.inner.create_lightning_address(request))
+    }
+
+    pub fn list_lightning_addresses(&self) -> Result<ListLightningAddressesResponse, APIError> {
+        self.runtime.block_on(self.inner.list_lightning_addresses())
+    }
+
+    pub fn get_lightning_address(
+        &self,
+        id: &str,
+    ) -> Result<CreateLightningAddressResponse, APIError> {
+        self.runtime.block_on(self.inner.get_lightning_address(id))
+    }
+
+    pub fn get_lightning_address_by_handle(
+        &self,
+        handle: &str,
+    ) -> Result<CreateLightningAddressResponse, APIError> {
+        self.runtime
+            .block_on(self.inner.get_lightning_address_by_handle(handle))
+    }
+
+    pub fn update_lightning_address(
+        &self,
+        id: &str,
+        request: UpdateLightningAddressRequest,
+    ) -> Result<CreateLightningAddressResponse, APIError> {
+        self.runtime
+            .block_on(self.inner.update_lightning_address(id, request))
+    }
+
+    pub fn delete_lightning_address(
+        &self,
+        id: &str,
+    ) -> Result<DeleteLightningAddressResponse, APIError> {
+        self.runtime
+            .block_on(self.inner.delete_lightning_address(id))
+    }
+
+    pub fn list_lightning_address_deposits(
+        &self,
+        id: &str,
+    ) -> Result<ListDepositsResponse, APIError> {
+        self.runtime
+            .block_on(self.inner.list_lightning_address_deposits(id))
+    }
+
+    pub fn create_onchain_address(
+        &self,
+        request: CreateOnChainAddressRequest,
+    ) -> Result<CreateOnChainAddressResponse, APIError> {
+        self.runtime
+            .block_on(self.inner.create_onchain_address(request))
+    }
+
+    pub fn list_onchain_addresses(&self) -> Result<ListOnChainAddressesResponse, APIError> {
+        self.runtime.block_on(self.inner.list_onchain_addresses())
+    }
+
+    pub fn get_onchain_address(&self, id: &str) -> Result<CreateOnChainAddressResponse, APIError> {
+        self.runtime.block_on(self.inner.get_onchain_address(id))
+    }
+
+    pub fn get_onchain_address_by_address(
+        &self,
+        address: &str,
+    ) -> Result<CreateOnChainAddressResponse, APIError> {
+        self.runtime
+            .block_on(self.inner.get_onchain_address_by_address(address))
+    }
+
+    pub fn list_onchain_address_deposits(
+        &self,
+        id: &str,
+    ) -> Result<ListDepositsResponse, APIError> {
+        self.runtime
+            .block_on(self.inner.list_onchain_address_deposits(id))
+    }
+
+    /// Watches an address created with
+    /// [`BitvoraClient::create_onchain_address`] for deposits.
+    pub fn watch_onchain_address(&self, address: &CreateOnChainAddressData) -> AddressWatcher {
+        AddressWatcher {
+            inner: self.inner.watch_onchain_address(address),
+            runtime: self.runtime.clone(),
+        }
+    }
+
+    pub fn get_withdrawal(&self, id: &str) -> Result<WithdrawResponse, APIError> {
+        self.runtime.block_on(self.inner.get_withdrawal(id))
+    }
+
+    pub fn get_deposit(&self, id: &str) -> Result<GetDepositResponse, APIError> {
+        self.runtime.block_on(self.inner.get_deposit(id))
+    }
+
+    pub fn list_withdrawals(
+        &self,
+        query: PaymentQuery,
+    ) -> Result<ListWithdrawalsResponse, APIError> {
+        self.runtime.block_on(self.inner.list_withdrawals(query))
+    }
+
+    pub fn list_deposits(&self, query: PaymentQuery) -> Result<ListDepositsResponse, APIError> {
+        self.runtime.block_on(self.inner.list_deposits(query))
+    }
+
+    pub fn get_balance(&self) -> Result<GetBalanceResponse, APIError> {
+        self.runtime.block_on(self.inner.get_balance())
+    }
+
+    pub fn get_transactions(&self) -> Result<GetTransactionsResponse, APIError> {
+        self.runtime.block_on(self.inner.get_transactions())
+    }
+
+    pub fn list_transactions(
+        &self,
+        query: TransactionQuery,
+    ) -> Result<ListTransactionsResponse, APIError> {
+        self.runtime.block_on(self.inner.list_transactions(query))
+    }
+
+    /// Iterates over every transaction matching `filter`, fetching pages as
+    /// the iterator is advanced. Stops after the first error.
+    pub fn transactions_iter(&self, filter: TransactionQuery) -> Transactions {
+        Iter::new(
+            TransactionStream::new(self.inner.clone(), filter).into_stream(),
+            &self.runtime,
+        )
+    }
+
+    /// Fetches the deposit or withdrawal behind transaction rows. See
+    /// [`Hydrator`].
+    pub fn hydrator(&self) -> Hydrator {
+        Hydrator {
+            inner: self.inner.hydrator(),
+            runtime: self.runtime.clone(),
+        }
+    }
+
+    pub fn get_account(&self) -> Result<AccountResponse, APIError> {
+        self.runtime.block_on(self.inner.get_account())
+    }
+
+    pub fn get_current_api_key(&self) -> Result<ApiKeyResponse, APIError> {
+        self.runtime.block_on(self.inner.get_current_api_key())
+    }
+
+    pub fn create_api_key(&self, request: CreateApiKeyRequest) -> Result<ApiKeyResponse, APIError> {
+        self.runtime.block_on(self.inner.create_api_key(request))
+    }
+
+    pub fn list_api_keys(&self) -> Result<ListApiKeysResponse, APIError> {
+        self.runtime.block_on(self.inner.list_api_keys())
+    }
+
+    pub fn revoke_api_key(&self, id: &str) -> Result<RevokeApiKeyResponse, APIError> {
+        self.runtime.block_on(self.inner.revoke_api_key(id))
+    }
+
+    pub fn create_webhook(
+        &self,
+        request: CreateWebhookRequest,
+    ) -> Result<WebhookResponse, APIError> {
+        self.runtime.block_on(self.inner.create_webhook(request))
+    }
+
+    pub fn list_webhooks(&self) -> Result<ListWebhooksResponse, APIError> {
+        self.runtime.block_on(self.inner.list_webhooks())
+    }
+
+    pub fn update_webhook(
+        &self,
+        id: &str,
+        request: UpdateWebhookRequest,
+    ) -> Result<WebhookResponse, APIError> {
+        self.runtime
+            .block_on(self.inner.update_webhook(id, request))
+    }
+
+    pub fn delete_webhook(&self, id: &str) -> Result<DeleteWebhookResponse, APIError> {
+        self.runtime.block_on(self.inner.delete_webhook(id))
+    }
+
+    pub fn rotate_webhook_secret(&self, id: &str) -> Result<WebhookResponse, APIError> {
+        self.runtime.block_on(self.inner.rotate_webhook_secret(id))
+    }
+
+    pub fn send_test_webhook(
+        &self,
+        id: &str,
+        event: &str,
+    ) -> Result<SendTestWebhookResponse, APIError> {
+        self.runtime
+            .block_on(self.inner.send_test_webhook(id, event))
+    }
+}
+
+impl fmt::Debug for BitvoraClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("BitvoraClient").field(&self.inner).finish()
+    }
+}
+
+/// The client's runtime, shut down in the background when the last clone
+/// (or handle or iterator created from one) is dropped. Dropping a `Runtime` the usual
+/// way blocks, which panics inside async code.
+#[derive(Debug)]
+struct OwnedRuntime(Option<Runtime>);
+
+impl OwnedRuntime {
+    fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.0
+            .as_ref()
+            .expect("the runtime is only taken on drop")
+            .block_on(future)
+    }
+}
+
+impl Drop for OwnedRuntime {
+    fn drop(&mut self) {
+        if let Some(runtime) = self.0.take() {
+            runtime.shutdown_background();
+        }
+    }
+}
+
+/// The items of one of the async client's streams, fetched as the iterator
+/// is advanced.
+pub struct Iter<T> {
+    stream: Pin<Box<dyn Stream<Item = Result<T, APIError>> + Send>>,
+    runtime: Arc<OwnedRuntime>,
+}
+
+impl<T> Iter<T> {
+    fn new(
+        stream: impl Stream<Item = Result<T, APIError>> + Send + 'static,
+        runtime: &Arc<OwnedRuntime>,
+    ) -> Self {
+        Self {
+            stream: Box::pin(stream),
+            runtime: runtime.clone(),
+        }
+    }
+}
+
+impl<T> Iterator for Iter<T> {
+    type Item = Result<T, APIError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.runtime.block_on(self.stream.next())
+    }
+}
+
+impl<T> fmt::Debug for Iter<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Iter").finish_non_exhaustive()
+    }
+}
+
+/// Every transaction matching a query. Created with
+/// [`BitvoraClient::transactions_iter`].
+pub type Transactions = Iter<Transaction>;
+
+/// Blocking counterpart of [`crate::PendingWithdrawal`].
+#[derive(Debug)]
+pub struct PendingWithdrawal {
+    inner: crate::PendingWithdrawal,
+    runtime: Arc<OwnedRuntime>,
+}
+
+impl PendingWithdrawal {
+    pub fn id(&self) -> &str {
+        self.inner.id()
+    }
+
+    /// The most recent state fetched, if any.
+    pub fn latest(&self) -> Option<&WithdrawData> {
+        self.inner.latest()
+    }
+
+    /// See [`crate::PendingWithdrawal::with_backoff`].
+    pub fn with_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.inner = self.inner.with_backoff(initial, max);
+        self
+    }
+
+    /// Blocks until the withdrawal's status is final. See
+    /// [`crate::PendingWithdrawal::wait_until_final`].
+    // Same error as the async method, which carries the last state seen.
+    #[allow(clippy::result_large_err)]
+    pub fn wait_until_final(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<WithdrawData, WaitError<WithdrawData>> {
+        self.runtime.block_on(self.inner.wait_until_final(timeout))
+    }
+}
+
+/// Blocking counterpart of [`crate::PendingInvoice`].
+#[derive(Debug)]
+pub struct PendingInvoice {
+    inner: crate::PendingInvoice,
+    runtime: Arc<OwnedRuntime>,
+}
+
+impl PendingInvoice {
+    pub fn id(&self) -> &str {
+        self.inner.id()
+    }
+
+    /// The most recent state fetched, if any.
+    pub fn latest(&self) -> Option<&CreateLightningInvoiceData> {
+        self.inner.latest()
+    }
+
+    /// See [`crate::PendingInvoice::with_expiry`].
+    pub fn with_expiry(mut self, expires_at: SystemTime) -> Self {
+        self.inner = self.inner.with_expiry(expires_at);
+        self
+    }
+
+    /// See [`crate::PendingInvoice::with_backoff`].
+    pub fn with_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.inner = self.inner.with_backoff(initial, max);
+        self
+    }
+
+    pub fn is_paid(&self) -> bool {
+        self.inner.is_paid()
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.inner.is_expired()
+    }
+
+    pub fn status(&self) -> InvoiceStatus {
+        self.inner.status()
+    }
+
+    /// Blocks until the invoice is paid, or fails with
+    /// [`WaitError::Expired`] when it expires first.
+    #[allow(clippy::result_large_err)]
+    pub fn wait_paid(
+        &mut self,
+    ) -> Result<CreateLightningInvoiceData, WaitError<CreateLightningInvoiceData>> {
+        self.runtime.block_on(self.inner.wait_paid())
+    }
+
+    /// Each new status, starting with the current one. See
+    /// [`crate::PendingInvoice::status_stream`].
+    pub fn status_iter(self) -> Iter<InvoiceStatus> {
+        Iter::new(self.inner.status_stream(), &self.runtime)
+    }
+}
+
+/// Blocking counterpart of [`crate::address::AddressWatcher`]. Iterating it
+/// blocks until the next deposit update and never ends on its own.
+#[derive(Debug)]
+pub struct AddressWatcher {
+    inner: crate::address::AddressWatcher,
+    runtime: Arc<OwnedRuntime>,
+}
+
+impl AddressWatcher {
+    pub fn address_id(&self) -> &str {
+        self.inner.address_id()
+    }
+
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.inner = self.inner.with_interval(interval);
+        self
+    }
+
+    /// Amount the address is meant to receive, to track partial payments.
+    pub fn with_expected_amount(mut self, sats: u64) -> Self {
+        self.inner = self.inner.with_expected_amount(sats);
+        self
+    }
+}
+
+impl IntoIterator for AddressWatcher {
+    type Item = Result<AddressDeposit, APIError>;
+    type IntoIter = Iter<AddressDeposit>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        Iter::new(self.inner.into_stream(), &self.runtime)
+    }
+}
+
+/// Blocking counterpart of [`crate::hydrate::Hydrator`]. Clones share the
+/// cache.
+#[derive(Debug, Clone)]
+pub struct Hydrator {
+    inner: crate::hydrate::Hydrator,
+    runtime: Arc<OwnedRuntime>,
+}
+
+impl Hydrator {
+    /// Maximum number of detail requests in flight. Defaults to 8.
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.inner = self.inner.with_concurrency(concurrency);
+        self
+    }
+
+    /// See [`crate::hydrate::Hydrator::with_cache_capacity`].
+    pub fn with_cache_capacity(mut self, capacity: usize) -> Self {
+        self.inner = self.inner.with_cache_capacity(capacity);
+        self
+    }
+
+    pub fn hydrate(&self, transaction: Transaction) -> Result<HydratedTransaction, APIError> {
+        self.runtime.block_on(self.inner.hydrate(transaction))
+    }
+
+    /// Hydrates a list of transactions, keeping their order. Fails on the
+    /// first error.
+    pub fn hydrate_all(
+        &self,
+        transactions: Vec<Transaction>,
+    ) -> Result<Vec<HydratedTransaction>, APIError> {
+        self.runtime.block_on(self.inner.hydrate_all(transactions))
+    }
+
+    /// Hydrates the transactions of [`BitvoraClient::transactions_iter`],
+    /// keeping their order and fetching details ahead of the caller.
+    pub fn hydrate_iter(self, transactions: Transactions) -> Iter<HydratedTransaction> {
+        Iter::new(
+            self.inner.hydrate_stream(transactions.stream),
+            &self.runtime,
+        )
+    }
+}
+
+/// Blocking counterpart of [`crate::ReadOnlyClient`]. Created with
+/// [`BitvoraClient::into_read_only`].
+#[derive(Debug, Clone)]
+pub struct ReadOnlyClient {
+    inner: crate::ReadOnlyClient,
+    runtime: Arc<OwnedRuntime>,
+}
+
+impl ReadOnlyClient {
+    /// See [`crate::ReadOnlyClient::verify_scopes`].
+    pub fn verify_scopes(&self) -> Result<(), ScopeError> {
+        self.runtime.block_on(self.inner.verify_scopes())
+    }
+
+    pub fn get_balance(&self) -> Result<GetBalanceResponse, APIError> {
+        self.runtime.block_on(self.inner.get_balance())
+    }
+
+    pub fn get_transactions(&self) -> Result<GetTransactionsResponse, APIError> {
+        self.runtime.block_on(self.inner.get_transactions())
+    }
+
+    pub fn list_transactions(
+        &self,
+        query: TransactionQuery,
+    ) -> Result<ListTransactionsResponse, APIError> {
+        self.runtime.block_on(self.inner.list_transactions(query))
+    }
+
+    pub fn transactions_iter(&self, filter: TransactionQuery) -> Transactions {
+        Iter::new(self.inner.transactions_stream(filter), &self.runtime)
+    }
+
+    pub fn hydrator(&self) -> Hydrator {
+        Hydrator {
+            inner: self.inner.hydrator(),
+            runtime: self.runtime.clone(),
+        }
+    }
+
+    pub fn get_withdrawal(&self, id: &str) -> Result<WithdrawResponse, APIError> {
+        self.runtime.block_on(self.inner.get_withdrawal(id))
+    }
+
+    pub fn get_deposit(&self, id: &str) -> Result<GetDepositResponse, APIError> {
+        self.runtime.block_on(self.inner.get_deposit(id))
+    }
+
+    pub fn list_withdrawals(
+        &self,
+        query: PaymentQuery,
+    ) -> Result<ListWithdrawalsResponse, APIError> {
+        self.runtime.block_on(self.inner.list_withdrawals(query))
+    }
+
+    pub fn list_deposits(&self, query: PaymentQuery) -> Result<ListDepositsResponse, APIError> {
+        self.runtime.block_on(self.inner.list_deposits(query))
+    }
+
+    pub fn estimate_withdrawal(
+        &self,
+        request: EstimateWithdrawalRequest,
+    ) -> Result<EstimateWithdrawalResponse, APIError> {
+        self.runtime
+            .block_on(self.inner.estimate_withdrawal(request))
+    }
+
+    pub fn get_lightning_invoice(
+        &self,
+        id: &str,
+    ) -> Result<CreateLightningInvoiceResponse, APIError> {
+        self.runtime.block_on(self.inner.get_lightning_invoice(id))
+    }
+
+    pub fn get_lightning_invoice_by_hash(
+        &self,
+        r_hash: &str,
+    ) -> Result<CreateLightningInvoiceResponse, APIError> {
+        self.runtime
+            .block_on(self.inner.get_lightning_invoice_by_hash(r_hash))
+    }
+
+    pub fn list_lightning_invoices(
+        &self,
+        query: LightningInvoiceQuery,
+    ) -> Result<ListLightningInvoicesResponse, APIError> {
+        self.runtime
+            .block_on(self.inner.list_lightning_invoices(query))
+    }
+
+    pub fn list_lightning_addresses(&self) -> Result<ListLightningAddressesResponse, APIError> {
+        self.runtime.block_on(self.inner.list_lightning_addresses())
+    }
+
+    pub fn get_lightning_address(
+        &self,
+        id: &str,
+    ) -> Result<CreateLightningAddressResponse, APIError> {
+        self.runtime.block_on(self.inner.get_lightning_address(id))
+    }
+
+    pub fn get_lightning_address_by_handle(
+        &self,
+        handle: &str,
+    ) -> Result<CreateLightningAddressResponse, APIError> {
+        self.runtime
+            .block_on(self.inner.get_lightning_address_by_handle(handle))
+    }
+
+    pub fn list_lightning_address_deposits(
+        &self,
+        id: &str,
+    ) -> Result<ListDepositsResponse, APIError> {
+        self.runtime
+            .block_on(self.inner.list_lightning_address_deposits(id))
+    }
+
+    pub fn list_onchain_addresses(&self) -> Result<ListOnChainAddressesResponse, APIError> {
+        self.runtime.block_on(self.inner.list_onchain_addresses())
+    }
+
+    pub fn get_onchain_address(&self, id: &str) -> Result<CreateOnChainAddressResponse, APIError> {
+        self.runtime.block_on(self.inner.get_onchain_address(id))
+    }
+
+    pub fn get_onchain_address_by_address(
+        &self,
+        address: &str,
+    ) -> Result<CreateOnChainAddressResponse, APIError> {
+        self.runtime
+            .block_on(self.inner.get_onchain_address_by_address(address))
+    }
+
+    pub fn list_onchain_address_deposits(
+        &self,
+        id: &str,
+    ) -> Result<ListDepositsResponse, APIError> {
+        self.runtime
+            .block_on(self.inner.list_onchain_address_deposits(id))
+    }
+
+    pub fn get_account(&self) -> Result<AccountResponse, APIError> {
+        self.runtime.block_on(self.inner.get_account())
+    }
+
+    pub fn get_current_api_key(&self) -> Result<ApiKeyResponse, APIError> {
+        self.runtime.block_on(self.inner.get_current_api_key())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{self, transaction, withdrawal, Reply};
+    use serde_json::json;
+
+    /// Serves a balance, and transactions `t0`..`t2` one per page.
+    fn client() -> BitvoraClient {
+        let (client, _) = test_support::client(|request| {
+            if request.path() != "/v1/transactions" {
+                return Reply::ok(json!({"balance": 42}));
+            }
+            let cursor: usize = request.param("cursor").map_or(0, |c| c.parse().unwrap());
+            Reply::json(json!({
+                "status": 200,
+                "message": "ok",
+                "data": [transaction(&format!("t{cursor}"), "deposit", "completed")],
+                "pagination": {"has_more": cursor < 2, "next_cursor": (cursor + 1).to_string()},
+            }))
+        });
+        BitvoraClient::from_async(client).unwrap()
+    }
+
+    #[test]
+    fn test_blocking_calls_without_a_runtime() {
+        let client = client();
+
+        assert_eq!(client.get_balance().unwrap().data.balance, 42);
+        let ids: Vec<_> = client
+            .transactions_iter(TransactionQuery::default())
+            .map(|transaction| transaction.unwrap().id)
+            .collect();
+        assert_eq!(ids, ["t0", "t1", "t2"]);
+
+        // Clones share the runtime and work from other threads.
+        let clone = client.clone();
+        let balance = std::thread::spawn(move || clone.get_balance().unwrap().data.balance);
+        assert_eq!(balance.join().unwrap(), 42);
+    }
+
+    #[test]
+    fn test_blocking_handles_and_read_only_client() {
+        let (client, requests) = test_support::client(|request| match request.path() {
+            "/v1/bitcoin/withdraw/confirm" => Reply::ok(withdrawal("w1", "pending")),
+            "/v1/transactions/withdrawals/w1" => Reply::ok(withdrawal("w1", "completed")),
+            _ => Reply::ok(json!({"balance": 42})),
+        });
+        let client = BitvoraClient::from_async(client).unwrap();
+
+        let mut pending = client
+            .withdraw_and_track(WithdrawRequest {
+                amount: 21.0,
+                currency: "sats".to_string(),
+                destination: "r".to_string(),
+                metadata: Default::default(),
+            })
+            .unwrap()
+            .with_backoff(Duration::from_millis(1), Duration::from_millis(1));
+        assert_eq!(pending.latest().unwrap().status, "pending");
+        let settled = pending.wait_until_final(Duration::from_secs(5)).unwrap();
+        assert_eq!(settled.status, "completed");
+
+        let read_only = client.into_read_only();
+        assert_eq!(read_only.get_balance().unwrap().data.balance, 42);
+        assert_eq!(
+            requests.targets(),
+            [
+                "/v1/bitcoin/withdraw/confirm",
+                "/v1/transactions/withdrawals/w1",
+                "/v1/transactions/balance",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_dropping_inside_async_code() {
+        let client = client();
+        let transactions = client.transactions_iter(TransactionQuery::default());
+        let clone = client.clone();
+
+        drop(transactions);
+        drop(client);
+        drop(clone);
+    }
+}
@@ -1,83 +1,170 @@
+use crate::address::AddressWatcher;
 use crate::errors::APIError;
+use crate::hydrate::Hydrator;
+use crate::invoice::PendingInvoice;
 use crate::models::{WithdrawRequest, WithdrawResponse};
+use crate::secret::{ApiKey, SecretProvider};
+use crate::service::{
+    CreateApiKey, CreateLightningAddress, CreateLightningInvoice, CreateOnChainAddress,
+    CreateWebhook, DeleteLightningAddress, DeleteWebhook, EstimateWithdrawal, GetAccount,
+    GetBalance, GetCurrentApiKey, GetDeposit, GetLightningAddress, GetLightningAddressByHandle,
+    GetLightningInvoice, GetLightningInvoiceByHash, GetOnChainAddress, GetOnChainAddressByAddress,
+    GetTransactions, GetWithdrawal, ListApiKeys, ListDeposits, ListLightningAddressDeposits,
+    ListLightningAddresses, ListLightningInvoices, ListOnChainAddressDeposits,
+    ListOnChainAddresses, ListTransactions, ListWebhooks, ListWithdrawals, Operation, RevokeApiKey,
+    RotateWebhookSecret, SendTestWebhook, UpdateLightningAddress, UpdateWebhook, Withdraw,
+};
+use crate::transactions::TransactionStream;
+use crate::withdrawal::PendingWithdrawal;
 use crate::{
-    CreateLightningAddressRequest, CreateLightningAddressResponse, CreateLightningInvoiceRequest,
-    CreateLightningInvoiceResponse, CreateOnChainAddressRequest, CreateOnChainAddressResponse,
+    AccountResponse, ApiKeyResponse, CreateApiKeyRequest, CreateLightningAddressRequest,
+    CreateLightningAddressResponse, CreateLightningInvoiceRequest, CreateLightningInvoiceResponse,
+    CreateOnChainAddressData, CreateOnChainAddressRequest, CreateOnChainAddressResponse,
+    CreateWebhookRequest, DeleteLightningAddressResponse, DeleteWebhookResponse,
     EstimateWithdrawalRequest, EstimateWithdrawalResponse, GetBalanceResponse, GetDepositResponse,
-    GetTransactionsResponse,
+    GetTransactionsResponse, LightningInvoiceQuery, ListApiKeysResponse, ListDepositsResponse,
+    ListLightningAddressesResponse, ListLightningInvoicesResponse, ListOnChainAddressesResponse,
+    ListTransactionsResponse, ListWebhooksResponse, ListWithdrawalsResponse, PaymentQuery,
+    RevokeApiKeyResponse, SendTestWebhookRequest, SendTestWebhookResponse, Transaction,
+    TransactionQuery, UpdateLightningAddressRequest, UpdateWebhookRequest, WebhookResponse,
 };
+use futures_util::Stream;
+use reqwest::header::CONTENT_TYPE;
 use reqwest::Client as HttpClient;
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tower::util::BoxCloneSyncService;
+use tower::{BoxError, Service, ServiceExt};
+
+type Transport = BoxCloneSyncService<reqwest::Request, reqwest::Response, APIError>;
 
+#[derive(Clone)]
 pub struct BitvoraClient {
     base_url: String,
-    api_key: String,
-    client: HttpClient,
+    api_key: Arc<dyn SecretProvider>,
+    client: HttpClient, // Only used to build requests; they are sent through `transport`
+    transport: Transport,
 }
 
 impl BitvoraClient {
     pub fn new(base_url: &str, api_key: &str) -> Self {
+        Self::from_secret_provider(base_url, ApiKey::new(api_key))
+    }
+
+    /// Creates a client that asks `provider` for the API key before every
+    /// request, so the key can be rotated without recreating the client.
+    pub fn from_secret_provider(base_url: &str, provider: impl SecretProvider + 'static) -> Self {
+        let client = HttpClient::new();
+        Self::from_parts(base_url, Arc::new(provider), client.clone(), client)
+    }
+
+    /// Creates a client that sends every request through `transport`.
+    ///
+    /// `reqwest::Client` is the default transport; wrap it (or any other
+    /// service) in tower layers such as timeouts, retries or concurrency limits
+    /// and pass the result here.
+    pub fn with_transport<S>(base_url: &str, api_key: &str, transport: S) -> Self
+    where
+        S: Service<reqwest::Request, Response = reqwest::Response> + Clone + Send + Sync + 'static,
+        S::Error: Into<BoxError>,
+        S::Future: Send + 'static,
+    {
+        Self::from_parts(
+            base_url,
+            Arc::new(ApiKey::new(api_key)),
+            HttpClient::new(),
+            transport,
+        )
+    }
+
+    fn from_parts<S>(
+        base_url: &str,
+        api_key: Arc<dyn SecretProvider>,
+        client: HttpClient,
+        transport: S,
+    ) -> Self
+    where
+        S: Service<reqwest::Request, Response = reqwest::Response> + Clone + Send + Sync + 'static,
+        S::Error: Into<BoxError>,
+        S::Future: Send + 'static,
+    {
+        let transport = transport.map_err(|err: S::Error| APIError::from_transport(err.into()));
+
         Self {
             base_url: base_url.to_string(),
-            api_key: api_key.to_string(),
-            client: HttpClient::new(),
+            api_key,
+            client,
+            transport: BoxCloneSyncService::new(transport),
         }
     }
 
-    pub async fn withdraw(&self, request: WithdrawRequest) -> Result<WithdrawResponse, APIError> {
-        let url = format!("{}/v1/bitcoin/withdraw/confirm", self.base_url);
+    /// Reads the API key from `provider` before every request instead of
+    /// using the key the client was created with, so it can be rotated.
+    pub fn with_secret_provider(mut self, provider: impl SecretProvider + 'static) -> Self {
+        self.api_key = Arc::new(provider);
+        self
+    }
 
-        let response = self
-            .client
-            .post(&url)
-            .bearer_auth(&self.api_key)
-            .json(&request) // Send the request body as JSON
-            .send()
-            .await?;
+    pub(crate) async fn execute<O: Operation>(
+        &self,
+        operation: O,
+    ) -> Result<O::Response, APIError> {
+        #[cfg(feature = "metrics")]
+        let start = std::time::Instant::now();
+
+        #[cfg(feature = "tracing")]
+        let result = {
+            let span = crate::trace::request_span(&operation);
+            crate::trace::instrument::<O, _>(span, self.send(operation)).await
+        };
+        #[cfg(not(feature = "tracing"))]
+        let result = self.send(operation).await;
 
-        let status = response.status();
-        let raw_body = response.text().await?;
+        #[cfg(feature = "metrics")]
+        crate::telemetry::record_call::<O>(start.elapsed(), &result);
 
-        if status.is_success() {
-            match serde_json::from_str::<WithdrawResponse>(&raw_body) {
-                Ok(withdraw_response) => Ok(withdraw_response),
-                Err(err) => {
-                    println!(
-                        "Failed to deserialize response: {}\nRaw body: {}",
-                        err, raw_body
-                    );
-                    Err(APIError::Serialization(err))
-                }
-            }
-        } else {
-            Err(APIError::BadRequest(status, raw_body))
-        }
+        result
     }
 
-    pub async fn estimate_withdrawal(
-        &self,
-        request: EstimateWithdrawalRequest,
-    ) -> Result<EstimateWithdrawalResponse, APIError> {
-        let url = format!("{}/v1/bitcoin/withdraw/estimate", self.base_url);
+    async fn send<O: Operation>(&self, operation: O) -> Result<O::Response, APIError> {
+        let path = operation.path();
+        // An empty segment means an id that `service::segment` couldn't
+        // encode; sending it would reach the parent endpoint.
+        if path.split('/').skip(1).any(str::is_empty) {
+            return Err(APIError::InvalidPath(path));
+        }
+        let url = format!("{}{}", self.base_url, path);
+        let api_key = self.api_key.api_key().await?;
 
-        let response = self
+        let mut builder = self
             .client
-            .post(&url)
-            .bearer_auth(&self.api_key)
-            .json(&request) // Send the request body as JSON
-            .send()
-            .await?;
+            .request(operation.method(), &url)
+            .bearer_auth(api_key.expose());
+        let query = operation.query();
+        if !query.is_empty() {
+            builder = builder.query(&query);
+        }
+        if let Some(body) = operation.body()? {
+            builder = builder.header(CONTENT_TYPE, "application/json").body(body);
+        }
+        let request = builder.build()?;
+
+        let response = self.transport.clone().oneshot(request).await?;
 
         let status = response.status();
+        #[cfg(feature = "tracing")]
+        tracing::Span::current().record("http.status", status.as_u16());
         let raw_body = response.text().await?;
 
         if status.is_success() {
-            match serde_json::from_str::<EstimateWithdrawalResponse>(&raw_body) {
-                Ok(estimate_response) => Ok(estimate_response),
+            match serde_json::from_str::<O::Response>(&raw_body) {
+                Ok(response) => Ok(response),
                 Err(err) => {
-                    println!(
-                        "Failed to deserialize response: {}\nRaw body: {}",
-                        err, raw_body
-                    );
+                    // The body may carry preimages, so only its length is
+                    // logged.
+                    #[cfg(feature = "tracing")]
+                    tracing::error!(error = %err, body_len = raw_body.len(), "failed to deserialize response");
                     Err(APIError::Serialization(err))
                 }
             }
@@ -87,222 +174,337 @@ impl BitvoraClient {
         }
     }
 
+    pub async fn withdraw(&self, request: WithdrawRequest) -> Result<WithdrawResponse, APIError> {
+        self.execute(Withdraw(request)).await
+    }
+
+    /// Like [`BitvoraClient::withdraw`], returning a handle that can wait for
+    /// the withdrawal to settle.
+    pub async fn withdraw_and_track(
+        &self,
+        request: WithdrawRequest,
+    ) -> Result<PendingWithdrawal, APIError> {
+        let response = self.withdraw(request).await?;
+        Ok(PendingWithdrawal::from_data(self.clone(), response.data))
+    }
+
+    /// A handle to an existing withdrawal.
+    pub fn pending_withdrawal(&self, id: &str) -> PendingWithdrawal {
+        PendingWithdrawal::new(self.clone(), id)
+    }
+
+    pub async fn estimate_withdrawal(
+        &self,
+        request: EstimateWithdrawalRequest,
+    ) -> Result<EstimateWithdrawalResponse, APIError> {
+        self.execute(EstimateWithdrawal(request)).await
+    }
+
     pub async fn create_lightning_invoice(
         &self,
         request: CreateLightningInvoiceRequest,
     ) -> Result<CreateLightningInvoiceResponse, APIError> {
-        let url = format!("{}/v1/bitcoin/deposit/lightning-invoice", self.base_url);
+        self.execute(CreateLightningInvoice(request)).await
+    }
 
-        let response = self
-            .client
-            .post(&url)
-            .bearer_auth(&self.api_key)
-            .json(&request) // Send the request body as JSON
-            .send()
-            .await?;
+    /// Like [`BitvoraClient::create_lightning_invoice`], returning a handle
+    /// that can wait for the invoice to be paid.
+    pub async fn create_lightning_invoice_and_track(
+        &self,
+        request: CreateLightningInvoiceRequest,
+    ) -> Result<PendingInvoice, APIError> {
+        // An expiry too far away to represent means waiting without one.
+        let expires_at = SystemTime::now().checked_add(Duration::from_secs(request.expiry_seconds));
+        let response = self.create_lightning_invoice(request).await?;
+        let pending = PendingInvoice::from_data(self.clone(), response.data);
+        Ok(match expires_at {
+            Some(expires_at) => pending.with_expiry(expires_at),
+            None => pending,
+        })
+    }
 
-        let status = response.status();
-        let raw_body = response.text().await?;
+    pub async fn get_lightning_invoice(
+        &self,
+        id: &str,
+    ) -> Result<CreateLightningInvoiceResponse, APIError> {
+        self.execute(GetLightningInvoice { id: id.to_string() })
+            .await
+    }
 
-        if status.is_success() {
-            match serde_json::from_str::<CreateLightningInvoiceResponse>(&raw_body) {
-                Ok(invoice_response) => Ok(invoice_response),
-                Err(err) => {
-                    println!(
-                        "Failed to deserialize response: {}\nRaw body: {}",
-                        err, raw_body
-                    );
-                    Err(APIError::Serialization(err))
-                }
-            }
-        } else {
-            // Capture the full response body for debugging
-            Err(APIError::BadRequest(status, raw_body))
-        }
+    /// Looks an invoice up by its payment hash, as found in the payment
+    /// request.
+    pub async fn get_lightning_invoice_by_hash(
+        &self,
+        r_hash: &str,
+    ) -> Result<CreateLightningInvoiceResponse, APIError> {
+        self.execute(GetLightningInvoiceByHash {
+            r_hash: r_hash.to_string(),
+        })
+        .await
+    }
+
+    /// Fetches one page of invoices matching `query`.
+    pub async fn list_lightning_invoices(
+        &self,
+        query: LightningInvoiceQuery,
+    ) -> Result<ListLightningInvoicesResponse, APIError> {
+        self.execute(ListLightningInvoices(query)).await
+    }
+
+    /// A handle to an existing lightning invoice.
+    pub fn pending_invoice(&self, id: &str) -> PendingInvoice {
+        PendingInvoice::new(self.clone(), id)
     }
 
     pub async fn create_lightning_address(
         &self,
         request: CreateLightningAddressRequest,
     ) -> Result<CreateLightningAddressResponse, APIError> {
-        let url = format!("{}/v1/bitcoin/deposit/lightning-address", self.base_url);
+        self.execute(CreateLightningAddress(request)).await
+    }
 
-        let response = self
-            .client
-            .post(&url)
-            .bearer_auth(&self.api_key)
-            .json(&request) // Send the request body as JSON
-            .send()
-            .await?;
+    pub async fn list_lightning_addresses(
+        &self,
+    ) -> Result<ListLightningAddressesResponse, APIError> {
+        self.execute(ListLightningAddresses).await
+    }
 
-        let status = response.status();
-        let raw_body = response.text().await?;
+    pub async fn get_lightning_address(
+        &self,
+        id: &str,
+    ) -> Result<CreateLightningAddressResponse, APIError> {
+        self.execute(GetLightningAddress { id: id.to_string() })
+            .await
+    }
 
-        if status.is_success() {
-            match serde_json::from_str::<CreateLightningAddressResponse>(&raw_body) {
-                Ok(invoice_response) => Ok(invoice_response),
-                Err(err) => {
-                    println!(
-                        "Failed to deserialize response: {}\nRaw body: {}",
-                        err, raw_body
-                    );
-                    Err(APIError::Serialization(err))
-                }
-            }
-        } else {
-            // Capture the full response body for debugging
-            Err(APIError::BadRequest(status, raw_body))
-        }
+    /// Looks an address up by its handle, the part before the `@`.
+    pub async fn get_lightning_address_by_handle(
+        &self,
+        handle: &str,
+    ) -> Result<CreateLightningAddressResponse, APIError> {
+        self.execute(GetLightningAddressByHandle {
+            handle: handle.to_string(),
+        })
+        .await
+    }
+
+    /// Replaces the metadata of a lightning address.
+    pub async fn update_lightning_address(
+        &self,
+        id: &str,
+        request: UpdateLightningAddressRequest,
+    ) -> Result<CreateLightningAddressResponse, APIError> {
+        self.execute(UpdateLightningAddress {
+            id: id.to_string(),
+            request,
+        })
+        .await
+    }
+
+    /// Deletes a lightning address so it stops receiving payments.
+    pub async fn delete_lightning_address(
+        &self,
+        id: &str,
+    ) -> Result<DeleteLightningAddressResponse, APIError> {
+        self.execute(DeleteLightningAddress { id: id.to_string() })
+            .await
+    }
+
+    /// Deposits received by a lightning address.
+    pub async fn list_lightning_address_deposits(
+        &self,
+        id: &str,
+    ) -> Result<ListDepositsResponse, APIError> {
+        self.execute(ListLightningAddressDeposits { id: id.to_string() })
+            .await
     }
 
     pub async fn create_onchain_address(
         &self,
         request: CreateOnChainAddressRequest,
     ) -> Result<CreateOnChainAddressResponse, APIError> {
-        let url = format!("{}/v1/bitcoin/deposit/on-chain", self.base_url);
+        self.execute(CreateOnChainAddress(request)).await
+    }
 
-        let response = self
-            .client
-            .post(&url)
-            .bearer_auth(&self.api_key)
-            .json(&request) // Send the request body as JSON
-            .send()
-            .await?;
+    pub async fn list_onchain_addresses(&self) -> Result<ListOnChainAddressesResponse, APIError> {
+        self.execute(ListOnChainAddresses).await
+    }
 
-        let status = response.status();
-        let raw_body = response.text().await?;
+    pub async fn get_onchain_address(
+        &self,
+        id: &str,
+    ) -> Result<CreateOnChainAddressResponse, APIError> {
+        self.execute(GetOnChainAddress { id: id.to_string() }).await
+    }
 
-        if status.is_success() {
-            match serde_json::from_str::<CreateOnChainAddressResponse>(&raw_body) {
-                Ok(invoice_response) => Ok(invoice_response),
-                Err(err) => {
-                    println!(
-                        "Failed to deserialize response: {}\nRaw body: {}",
-                        err, raw_body
-                    );
-                    Err(APIError::Serialization(err))
-                }
-            }
-        } else {
-            // Capture the full response body for debugging
-            Err(APIError::BadRequest(status, raw_body))
-        }
+    /// Looks an address up by the address string itself, e.g. one a customer
+    /// paid to.
+    pub async fn get_onchain_address_by_address(
+        &self,
+        address: &str,
+    ) -> Result<CreateOnChainAddressResponse, APIError> {
+        self.execute(GetOnChainAddressByAddress {
+            address: address.to_string(),
+        })
+        .await
+    }
+
+    /// Deposits received by an on-chain address.
+    pub async fn list_onchain_address_deposits(
+        &self,
+        id: &str,
+    ) -> Result<ListDepositsResponse, APIError> {
+        self.execute(ListOnChainAddressDeposits { id: id.to_string() })
+            .await
+    }
+
+    /// Watches an address created with
+    /// [`BitvoraClient::create_onchain_address`] for deposits.
+    pub fn watch_onchain_address(&self, address: &CreateOnChainAddressData) -> AddressWatcher {
+        AddressWatcher::new(self.clone(), address)
     }
 
     pub async fn get_withdrawal(&self, id: &str) -> Result<WithdrawResponse, APIError> {
-        let url = format!("{}/v1/transactions/withdrawals/{}", self.base_url, id);
+        self.execute(GetWithdrawal { id: id.to_string() }).await
+    }
 
-        let response = self
-            .client
-            .get(&url)
-            .bearer_auth(&self.api_key)
-            .send()
-            .await?;
+    pub async fn get_deposit(&self, id: &str) -> Result<GetDepositResponse, APIError> {
+        self.execute(GetDeposit { id: id.to_string() }).await
+    }
 
-        let status = response.status();
-        let raw_body = response.text().await?;
+    /// Fetches one page of withdrawals matching `query`, with full details.
+    pub async fn list_withdrawals(
+        &self,
+        query: PaymentQuery,
+    ) -> Result<ListWithdrawalsResponse, APIError> {
+        self.execute(ListWithdrawals(query)).await
+    }
 
-        if status.is_success() {
-            match serde_json::from_str::<WithdrawResponse>(&raw_body) {
-                Ok(withdraw_response) => Ok(withdraw_response),
-                Err(err) => {
-                    println!(
-                        "Failed to deserialize response: {}\nRaw body: {}",
-                        err, raw_body
-                    );
-                    Err(APIError::Serialization(err))
-                }
-            }
-        } else {
-            Err(APIError::BadRequest(status, raw_body))
-        }
+    /// Fetches one page of deposits matching `query`, with full details.
+    pub async fn list_deposits(
+        &self,
+        query: PaymentQuery,
+    ) -> Result<ListDepositsResponse, APIError> {
+        self.execute(ListDeposits(query)).await
     }
 
-    pub async fn get_deposit(&self, id: &str) -> Result<GetDepositResponse, APIError> {
-        let url = format!("{}/v1/transactions/deposits/{}", self.base_url, id);
+    pub async fn get_balance(&self) -> Result<GetBalanceResponse, APIError> {
+        self.execute(GetBalance).await
+    }
 
-        let response = self
-            .client
-            .get(&url)
-            .bearer_auth(&self.api_key)
-            .send()
-            .await?;
+    /// Fetches every transaction at once. Accounts with a long history should
+    /// page through [`BitvoraClient::list_transactions`] instead.
+    pub async fn get_transactions(&self) -> Result<GetTransactionsResponse, APIError> {
+        self.execute(GetTransactions).await
+    }
 
-        let status = response.status();
-        let raw_body = response.text().await?;
+    /// Fetches one page of transactions matching `query`. Pass the response's
+    /// [`ListTransactionsResponse::next_query`] to get the next page.
+    pub async fn list_transactions(
+        &self,
+        query: TransactionQuery,
+    ) -> Result<ListTransactionsResponse, APIError> {
+        self.execute(ListTransactions(query)).await
+    }
 
-        if status.is_success() {
-            match serde_json::from_str::<GetDepositResponse>(&raw_body) {
-                Ok(withdraw_response) => Ok(withdraw_response),
-                Err(err) => {
-                    println!(
-                        "Failed to deserialize response: {}\nRaw body: {}",
-                        err, raw_body
-                    );
-                    Err(APIError::Serialization(err))
-                }
-            }
-        } else {
-            Err(APIError::BadRequest(status, raw_body))
-        }
+    /// Streams every transaction matching `filter`, fetching pages as they
+    /// are consumed. See [`TransactionStream`] for prefetching and resuming.
+    pub fn transactions_stream(
+        &self,
+        filter: TransactionQuery,
+    ) -> impl Stream<Item = Result<Transaction, APIError>> + Send {
+        TransactionStream::new(self.clone(), filter).into_stream()
     }
 
-    pub async fn get_balance(&self) -> Result<GetBalanceResponse, APIError> {
-        let url = format!("{}/v1/transactions/balance", self.base_url);
+    /// Fetches the deposit or withdrawal behind transaction rows. See
+    /// [`Hydrator`].
+    pub fn hydrator(&self) -> Hydrator {
+        Hydrator::new(self.clone())
+    }
 
-        let response = self
-            .client
-            .get(&url)
-            .bearer_auth(&self.api_key)
-            .send()
-            .await?;
+    /// The company the API key belongs to.
+    pub async fn get_account(&self) -> Result<AccountResponse, APIError> {
+        self.execute(GetAccount).await
+    }
 
-        let status = response.status();
-        let raw_body = response.text().await?;
+    /// The API key the client is using, with its scopes.
+    pub async fn get_current_api_key(&self) -> Result<ApiKeyResponse, APIError> {
+        self.execute(GetCurrentApiKey).await
+    }
 
-        if status.is_success() {
-            match serde_json::from_str::<GetBalanceResponse>(&raw_body) {
-                Ok(balance_response) => Ok(balance_response),
-                Err(err) => {
-                    println!(
-                        "Failed to deserialize response: {}\nRaw body: {}",
-                        err, raw_body
-                    );
-                    Err(APIError::Serialization(err))
-                }
-            }
-        } else {
-            Err(APIError::BadRequest(status, raw_body))
-        }
+    /// Creates an API key limited to `request.scopes`. The key itself is only
+    /// returned in this response.
+    pub async fn create_api_key(
+        &self,
+        request: CreateApiKeyRequest,
+    ) -> Result<ApiKeyResponse, APIError> {
+        self.execute(CreateApiKey(request)).await
     }
 
-    pub async fn get_transactions(&self) -> Result<GetTransactionsResponse, APIError> {
-        let url = format!("{}/v1/transactions", self.base_url);
+    pub async fn list_api_keys(&self) -> Result<ListApiKeysResponse, APIError> {
+        self.execute(ListApiKeys).await
+    }
 
-        let response = self
-            .client
-            .get(&url)
-            .bearer_auth(&self.api_key)
-            .send()
-            .await?;
+    pub async fn revoke_api_key(&self, id: &str) -> Result<RevokeApiKeyResponse, APIError> {
+        self.execute(RevokeApiKey { id: id.to_string() }).await
+    }
 
-        let status = response.status();
-        let raw_body = response.text().await?;
+    /// Registers a webhook URL. The response carries the signing secret, which
+    /// is not returned again.
+    pub async fn create_webhook(
+        &self,
+        request: CreateWebhookRequest,
+    ) -> Result<WebhookResponse, APIError> {
+        self.execute(CreateWebhook(request)).await
+    }
 
-        if status.is_success() {
-            match serde_json::from_str::<GetTransactionsResponse>(&raw_body) {
-                Ok(transactions_response) => Ok(transactions_response),
-                Err(err) => {
-                    println!(
-                        "Failed to deserialize response: {}\nRaw body: {}",
-                        err, raw_body
-                    );
-                    Err(APIError::Serialization(err))
-                }
-            }
-        } else {
-            Err(APIError::BadRequest(status, raw_body))
-        }
+    pub async fn list_webhooks(&self) -> Result<ListWebhooksResponse, APIError> {
+        self.execute(ListWebhooks).await
+    }
+
+    pub async fn update_webhook(
+        &self,
+        id: &str,
+        request: UpdateWebhookRequest,
+    ) -> Result<WebhookResponse, APIError> {
+        self.execute(UpdateWebhook {
+            id: id.to_string(),
+            request,
+        })
+        .await
+    }
+
+    pub async fn delete_webhook(&self, id: &str) -> Result<DeleteWebhookResponse, APIError> {
+        self.execute(DeleteWebhook { id: id.to_string() }).await
+    }
+
+    /// Replaces the webhook's signing secret; the new one is in the response.
+    pub async fn rotate_webhook_secret(&self, id: &str) -> Result<WebhookResponse, APIError> {
+        self.execute(RotateWebhookSecret { id: id.to_string() })
+            .await
+    }
+
+    /// Asks Bitvora to deliver a sample event of type `event` to the webhook.
+    pub async fn send_test_webhook(
+        &self,
+        id: &str,
+        event: &str,
+    ) -> Result<SendTestWebhookResponse, APIError> {
+        self.execute(SendTestWebhook {
+            id: id.to_string(),
+            request: SendTestWebhookRequest {
+                event: event.to_string(),
+            },
+        })
+        .await
+    }
+}
+
+impl fmt::Debug for BitvoraClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BitvoraClient")
+            .field("base_url", &self.base_url)
+            .finish_non_exhaustive()
     }
 }
 
@@ -0,0 +1,635 @@
+//! Wire-level debug logging.
+//!
+//! [`WireLog`] records the most recent HTTP exchanges that pass through its
+//! layer, with the API key, preimages, webhook secrets, minted API keys,
+//! metadata values and identifying query parameters masked, and can export
+//! the session in HAR format for sharing with support:
+//!
+//! ```ignore
+//! let log = WireLog::new();
+//! let transport = ServiceBuilder::new()
+//!     .layer(log.layer())
+//!     .service(reqwest::Client::new());
+//! let client = BitvoraClient::with_transport(base_url, &api_key, transport);
+//! // ... reproduce the problem ...
+//! log.write_har("bitvora.har")?;
+//! ```
+
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::future::Future;
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tower::{BoxError, Layer, Service};
+
+const REDACTED: &str = "[REDACTED]";
+
+/// Body fields whose values are always masked.
+pub const SECRET_FIELDS: &[&str] = &[
+    "r_preimage",
+    "payment_preimage",
+    "preimage",
+    // Webhook signing secrets.
+    "secret",
+    // API keys returned when they are created.
+    "key",
+];
+
+/// Query parameters whose values are kept; all others are masked, since
+/// they may hold metadata filters, payment hashes or recipients.
+const PLAIN_QUERY_PARAMS: &[&str] = &[
+    "limit",
+    "cursor",
+    "offset",
+    "status",
+    "type",
+    "rail_type",
+    "settled",
+    "expired",
+    "created_after",
+    "created_before",
+];
+
+/// Path segments naming an object by a value the user chose or owns, such
+/// as an on-chain address or lightning address handle. The segment after
+/// them is masked.
+const IDENTIFYING_SEGMENTS: &[&str] = &["address", "handle"];
+
+/// Number of exchanges kept by default.
+pub const DEFAULT_CAPACITY: usize = 1_000;
+
+/// One recorded request/response pair, already redacted.
+#[derive(Clone, Debug)]
+pub struct Exchange {
+    pub started_at: SystemTime,
+    pub duration: Duration,
+    pub method: String,
+    pub url: String,
+    pub request_headers: Vec<(String, String)>,
+    pub request_body: Option<String>,
+    pub status: Option<u16>,
+    pub response_headers: Vec<(String, String)>,
+    pub response_body: Option<String>,
+    pub error: Option<String>,
+}
+
+/// A recorded debug session. Clones share the same recording.
+///
+/// Only the last [`DEFAULT_CAPACITY`] exchanges are kept unless configured
+/// otherwise. Nothing is printed; with the `tracing` feature each exchange is
+/// also emitted as a `debug` event on the `bitvora::wire` target.
+#[derive(Clone, Debug)]
+pub struct WireLog {
+    exchanges: Arc<Mutex<VecDeque<Exchange>>>,
+    capacity: usize,
+    secret_fields: Arc<Vec<String>>,
+}
+
+impl Default for WireLog {
+    fn default() -> Self {
+        Self {
+            exchanges: Arc::default(),
+            capacity: DEFAULT_CAPACITY,
+            secret_fields: Arc::new(
+                SECRET_FIELDS
+                    .iter()
+                    .map(|field| field.to_string())
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl WireLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keeps at most `capacity` exchanges, dropping the oldest first.
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Masks `fields` in bodies as well as [`SECRET_FIELDS`].
+    pub fn with_secret_fields<I, F>(mut self, fields: I) -> Self
+    where
+        I: IntoIterator<Item = F>,
+        F: Into<String>,
+    {
+        let mut all = self.secret_fields.as_ref().clone();
+        all.extend(fields.into_iter().map(Into::into));
+        self.secret_fields = Arc::new(all);
+        self
+    }
+
+    /// Layer that records and logs every exchange passing through it.
+    pub fn layer(&self) -> WireLogLayer {
+        WireLogLayer { log: self.clone() }
+    }
+
+    pub fn exchanges(&self) -> Vec<Exchange> {
+        self.exchanges.lock().unwrap().iter().cloned().collect()
+    }
+
+    pub fn clear(&self) {
+        self.exchanges.lock().unwrap().clear();
+    }
+
+    /// The session as a HAR 1.2 document.
+    pub fn to_har(&self) -> Value {
+        let entries: Vec<Value> = self.exchanges().iter().map(har_entry).collect();
+
+        json!({
+            "log": {
+                "version": "1.2",
+                "creator": {
+                    "name": env!("CARGO_PKG_NAME"),
+                    "version": env!("CARGO_PKG_VERSION"),
+                },
+                "entries": entries,
+            }
+        })
+    }
+
+    pub fn write_har(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        let har = serde_json::to_vec_pretty(&self.to_har())?;
+        std::fs::write(path, har)
+    }
+
+    fn record(&self, exchange: Exchange) {
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            target: "bitvora::wire",
+            method = %exchange.method,
+            url = %exchange.url,
+            status = exchange.status,
+            request_body = exchange.request_body.as_deref().unwrap_or(""),
+            response_body = exchange.response_body.as_deref().unwrap_or(""),
+            error = exchange.error.as_deref(),
+            "http exchange"
+        );
+
+        if self.capacity == 0 {
+            return;
+        }
+        let mut exchanges = self.exchanges.lock().unwrap();
+        if exchanges.len() >= self.capacity {
+            exchanges.pop_front();
+        }
+        exchanges.push_back(exchange);
+    }
+
+    fn redact_body(&self, body: &[u8]) -> String {
+        redact_body_fields(body, &self.secret_fields)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct WireLogLayer {
+    log: WireLog,
+}
+
+impl<S> Layer<S> for WireLogLayer {
+    type Service = WireLogService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        WireLogService {
+            inner,
+            log: self.log.clone(),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct WireLogService<S> {
+    inner: S,
+    log: WireLog,
+}
+
+impl<S> Service<reqwest::Request> for WireLogService<S>
+where
+    S: Service<reqwest::Request, Response = reqwest::Response> + Clone + Send + 'static,
+    S::Error: Into<BoxError>,
+    S::Future: Send + 'static,
+{
+    type Response = reqwest::Response;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<reqwest::Response, BoxError>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, request: reqwest::Request) -> Self::Future {
+        // Use the instance that was driven to readiness, leaving a fresh clone
+        // in its place.
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let log = self.log.clone();
+
+        let mut exchange = Exchange {
+            started_at: SystemTime::now(),
+            duration: Duration::ZERO,
+            method: request.method().to_string(),
+            url: redact_url(request.url()),
+            request_headers: redact_headers(request.headers()),
+            request_body: request
+                .body()
+                .and_then(|body| body.as_bytes())
+                .map(|body| log.redact_body(body)),
+            status: None,
+            response_headers: Vec::new(),
+            response_body: None,
+            error: None,
+        };
+
+        Box::pin(async move {
+            let start = Instant::now();
+            let response = match inner.call(request).await {
+                Ok(response) => response,
+                Err(err) => {
+                    let err = err.into();
+                    exchange.duration = start.elapsed();
+                    exchange.error = Some(err.to_string());
+                    log.record(exchange);
+                    return Err(err);
+                }
+            };
+
+            // The body has to be buffered to be logged, so the response is
+            // rebuilt around it before being handed back.
+            let status = response.status();
+            let version = response.version();
+            let headers = response.headers().clone();
+            let body = match response.bytes().await {
+                Ok(body) => body,
+                Err(err) => {
+                    exchange.duration = start.elapsed();
+                    exchange.status = Some(status.as_u16());
+                    exchange.error = Some(err.to_string());
+                    log.record(exchange);
+                    return Err(err.into());
+                }
+            };
+
+            exchange.duration = start.elapsed();
+            exchange.status = Some(status.as_u16());
+            exchange.response_headers = redact_headers(&headers);
+            exchange.response_body = Some(log.redact_body(&body));
+            log.record(exchange);
+
+            let mut rebuilt = http::Response::new(body);
+            *rebuilt.status_mut() = status;
+            *rebuilt.version_mut() = version;
+            *rebuilt.headers_mut() = headers;
+            Ok(reqwest::Response::from(rebuilt))
+        })
+    }
+}
+
+fn redact_headers(headers: &reqwest::header::HeaderMap) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            let value = match name.as_str() {
+                "authorization" | "cookie" | "set-cookie" => REDACTED.to_string(),
+                _ => String::from_utf8_lossy(value.as_bytes()).into_owned(),
+            };
+            (name.to_string(), value)
+        })
+        .collect()
+}
+
+/// Masks query parameter values, except for paging and status filters, and
+/// path segments that name an object by address or handle.
+pub fn redact_url(url: &reqwest::Url) -> String {
+    let mut redacted = url.clone();
+    let segments: Vec<String> = url
+        .path_segments()
+        .map(|segments| segments.map(str::to_string).collect())
+        .unwrap_or_default();
+    if let Ok(mut path) = redacted.path_segments_mut() {
+        path.clear();
+        let mut previous = "";
+        for segment in &segments {
+            if IDENTIFYING_SEGMENTS.contains(&previous) {
+                path.push(REDACTED);
+            } else {
+                path.push(segment);
+            }
+            previous = segment;
+        }
+    }
+    if url.query().is_some() {
+        let pairs: Vec<(String, String)> = url
+            .query_pairs()
+            .map(|(name, value)| {
+                let value = if PLAIN_QUERY_PARAMS.contains(&name.as_ref()) {
+                    value.into_owned()
+                } else {
+                    REDACTED.to_string()
+                };
+                (name.into_owned(), value)
+            })
+            .collect();
+        redacted.query_pairs_mut().clear().extend_pairs(pairs);
+    }
+    redacted.to_string()
+}
+
+/// Masks [`SECRET_FIELDS`] and metadata values in a JSON body. Bodies that
+/// aren't JSON are returned as-is.
+pub fn redact_body(body: &[u8]) -> String {
+    let fields: Vec<String> = SECRET_FIELDS
+        .iter()
+        .map(|field| field.to_string())
+        .collect();
+    redact_body_fields(body, &fields)
+}
+
+fn redact_body_fields(body: &[u8], fields: &[String]) -> String {
+    match serde_json::from_slice::<Value>(body) {
+        Ok(mut value) => {
+            redact_value(&mut value, fields);
+            value.to_string()
+        }
+        Err(_) => String::from_utf8_lossy(body).into_owned(),
+    }
+}
+
+fn redact_value(value: &mut Value, fields: &[String]) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                if fields.iter().any(|field| field == key) {
+                    *value = Value::String(REDACTED.to_string());
+                } else if key == "metadata" {
+                    if let Value::Object(metadata) = value {
+                        for value in metadata.values_mut() {
+                            *value = Value::String(REDACTED.to_string());
+                        }
+                    }
+                } else {
+                    redact_value(value, fields);
+                }
+            }
+        }
+        Value::Array(values) => values
+            .iter_mut()
+            .for_each(|value| redact_value(value, fields)),
+        _ => {}
+    }
+}
+
+fn har_entry(exchange: &Exchange) -> Value {
+    let headers = |headers: &[(String, String)]| -> Vec<Value> {
+        headers
+            .iter()
+            .map(|(name, value)| json!({ "name": name, "value": value }))
+            .collect()
+    };
+    let header = |headers: &[(String, String)], name: &str| -> String {
+        headers
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.clone())
+            .unwrap_or_default()
+    };
+
+    let mut request = json!({
+        "method": exchange.method,
+        "url": exchange.url,
+        "httpVersion": "HTTP/1.1",
+        "cookies": [],
+        "headers": headers(&exchange.request_headers),
+        "queryString": query_string(&exchange.url),
+        "headersSize": -1,
+        "bodySize": exchange.request_body.as_ref().map_or(0, |body| body.len()),
+    });
+    if let Some(body) = &exchange.request_body {
+        request["postData"] = json!({
+            "mimeType": header(&exchange.request_headers, "content-type"),
+            "text": body,
+        });
+    }
+
+    let response_body = exchange.response_body.clone().unwrap_or_default();
+    let millis = exchange.duration.as_secs_f64() * 1000.0;
+
+    let mut entry = json!({
+        "startedDateTime": rfc3339(exchange.started_at),
+        "time": millis,
+        "request": request,
+        "response": {
+            "status": exchange.status.unwrap_or(0),
+            "statusText": exchange
+                .status
+                .and_then(|status| reqwest::StatusCode::from_u16(status).ok())
+                .and_then(|status| status.canonical_reason())
+                .unwrap_or(""),
+            "httpVersion": "HTTP/1.1",
+            "cookies": [],
+            "headers": headers(&exchange.response_headers),
+            "content": {
+                "size": response_body.len(),
+                "mimeType": header(&exchange.response_headers, "content-type"),
+                "text": response_body,
+            },
+            "redirectURL": "",
+            "headersSize": -1,
+            "bodySize": response_body.len(),
+        },
+        "cache": {},
+        "timings": { "send": 0, "wait": millis, "receive": 0 },
+    });
+    if let Some(error) = &exchange.error {
+        entry["comment"] = json!(error);
+    }
+    entry
+}
+
+fn query_string(url: &str) -> Vec<Value> {
+    reqwest::Url::parse(url)
+        .map(|url| {
+            url.query_pairs()
+                .map(|(name, value)| json!({ "name": name, "value": value }))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Formats a timestamp as RFC 3339 in UTC with millisecond precision.
+pub(crate) fn rfc3339(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since_epoch.as_secs();
+    let (days, secs_of_day) = ((secs / 86_400) as i64, secs % 86_400);
+
+    // Civil-from-days, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        secs_of_day / 3_600,
+        secs_of_day % 3_600 / 60,
+        secs_of_day % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BitvoraClient;
+    use crate::CreateLightningInvoiceRequest;
+    use std::collections::HashMap;
+    use tower::{service_fn, ServiceBuilder};
+
+    #[test]
+    fn test_redact_body_masks_preimages_and_metadata() {
+        let body = br#"{"data":{"r_preimage":"abc","lightning_payment":{"payment_preimage":"def","htlcs":[{"preimage":"ghi"}]},"metadata":{"userID":"1234"},"id":"x"}}"#;
+
+        let redacted: Value = serde_json::from_str(&redact_body(body)).unwrap();
+
+        assert_eq!(redacted["data"]["r_preimage"], REDACTED);
+        assert_eq!(
+            redacted["data"]["lightning_payment"]["payment_preimage"],
+            REDACTED
+        );
+        assert_eq!(
+            redacted["data"]["lightning_payment"]["htlcs"][0]["preimage"],
+            REDACTED
+        );
+        assert_eq!(redacted["data"]["metadata"]["userID"], REDACTED);
+        assert_eq!(redacted["data"]["id"], "x");
+    }
+
+    #[test]
+    fn test_secrets_and_keys_are_masked() {
+        let body = br#"{"data":[{"id":"wh1","secret":"whsec_abc"},{"id":"k1","key":"bv_live_abc","token":"t"}]}"#;
+        let log = WireLog::new().with_secret_fields(["token"]);
+
+        let redacted: Value = serde_json::from_str(&log.redact_body(body)).unwrap();
+
+        assert_eq!(redacted["data"][0]["secret"], REDACTED);
+        assert_eq!(redacted["data"][1]["key"], REDACTED);
+        assert_eq!(redacted["data"][1]["token"], REDACTED);
+        assert_eq!(redacted["data"][1]["id"], "k1");
+    }
+
+    #[test]
+    fn test_redact_url_masks_filters_and_lookups() {
+        let url = reqwest::Url::parse(
+            "https://api.example.com/v1/bitcoin/deposit/on-chain/address/tb1qabc?limit=10&metadata%5Border%5D=42&r_hash=abc",
+        )
+        .unwrap();
+
+        let redacted = redact_url(&url);
+
+        assert!(!redacted.contains("tb1qabc"));
+        assert!(!redacted.contains("42"));
+        assert!(!redacted.contains("r_hash=abc"));
+        assert!(redacted.contains("limit=10"));
+        assert!(
+            redacted.starts_with("https://api.example.com/v1/bitcoin/deposit/on-chain/address/")
+        );
+    }
+
+    #[test]
+    fn test_only_the_latest_exchanges_are_kept() {
+        let log = WireLog::new().with_capacity(2);
+        for i in 0..3 {
+            log.record(Exchange {
+                started_at: SystemTime::now(),
+                duration: Duration::ZERO,
+                method: "GET".to_string(),
+                url: format!("https://api.example.com/{i}"),
+                request_headers: Vec::new(),
+                request_body: None,
+                status: Some(200),
+                response_headers: Vec::new(),
+                response_body: None,
+                error: None,
+            });
+        }
+
+        let urls: Vec<_> = log.exchanges().into_iter().map(|e| e.url).collect();
+        assert_eq!(
+            urls,
+            ["https://api.example.com/1", "https://api.example.com/2"]
+        );
+    }
+
+    #[test]
+    fn test_rfc3339() {
+        let time = UNIX_EPOCH + Duration::from_millis(1_709_210_096_789);
+        assert_eq!(rfc3339(time), "2024-02-29T12:34:56.789Z");
+    }
+
+    #[tokio::test]
+    async fn test_exchanges_are_recorded_redacted_and_exported() {
+        let log = WireLog::new();
+        let transport = ServiceBuilder::new()
+            .layer(log.layer())
+            .service(service_fn(|_request: reqwest::Request| async move {
+                let body = r#"{"status":201,"message":"ok","data":{"id":"inv1","node_id":"n","memo":"m","r_preimage":"topsecret","r_hash":"h","amount_sats":21,"settled":false,"payment_request":"lnbc","metadata":{"userID":"1234"},"lightning_address_id":null}}"#;
+                Ok::<_, BoxError>(reqwest::Response::from(
+                    http::Response::builder()
+                        .status(201)
+                        .header("content-type", "application/json")
+                        .body(body)
+                        .unwrap(),
+                ))
+            }));
+        let client =
+            BitvoraClient::with_transport("https://api.example.com", "secret-key", transport);
+
+        let mut metadata = HashMap::new();
+        metadata.insert("userID".to_string(), "1234".to_string());
+        let response = client
+            .create_lightning_invoice(CreateLightningInvoiceRequest {
+                amount: 21.0,
+                currency: "sats".to_string(),
+                description: "test".to_string(),
+                expiry_seconds: 60,
+                metadata: Some(metadata),
+            })
+            .await
+            .unwrap();
+
+        // The caller still sees the real response.
+        assert_eq!(response.data.r_preimage, "topsecret");
+        client
+            .get_lightning_invoice_by_hash("hash-1234")
+            .await
+            .unwrap();
+
+        let har = log.to_har().to_string();
+        assert!(har.contains("inv1"));
+        assert!(!har.contains("topsecret"));
+        assert!(!har.contains("secret-key"));
+        assert!(!har.contains("1234"));
+        let entry = &log.to_har()["log"]["entries"][1];
+        assert_eq!(entry["request"]["queryString"][0]["name"], "r_hash");
+        assert_eq!(entry["request"]["queryString"][0]["value"], REDACTED);
+
+        let entry = &log.to_har()["log"]["entries"][0];
+        assert_eq!(entry["request"]["method"], "POST");
+        assert_eq!(entry["response"]["status"], 201);
+        assert_eq!(entry["response"]["statusText"], "Created");
+    }
+}
@@ -1,5 +1,8 @@
+use crate::models::ApiKeyScope;
+use crate::secret::SecretError;
 use reqwest::StatusCode;
 use std::fmt;
+use tower::BoxError;
 
 #[derive(Debug)]
 pub enum APIError {
@@ -7,6 +10,38 @@ pub enum APIError {
     Reqwest(reqwest::Error),
     Serialization(serde_json::Error),
     BadRequest(StatusCode, String), // New error variant to capture status and response body
+    Transport(BoxError),            // Errors raised by tower layers wrapping the transport
+    Secret(SecretError),
+    /// An id was empty or only dots, so the request path can't name it.
+    InvalidPath(String),
+}
+
+impl APIError {
+    /// Short, stable name of the variant, suitable for logs and metric labels.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            APIError::Http(_) => "http",
+            APIError::Reqwest(_) => "reqwest",
+            APIError::Serialization(_) => "serialization",
+            APIError::BadRequest(_, _) => "bad_request",
+            APIError::Transport(_) => "transport",
+            APIError::Secret(_) => "secret",
+            APIError::InvalidPath(_) => "invalid_path",
+        }
+    }
+
+    /// Recovers the concrete error from a boxed transport error, so errors that
+    /// merely passed through a tower layer keep their original variant.
+    pub(crate) fn from_transport(err: BoxError) -> Self {
+        let err = match err.downcast::<APIError>() {
+            Ok(err) => return *err,
+            Err(err) => err,
+        };
+        match err.downcast::<reqwest::Error>() {
+            Ok(err) => APIError::Reqwest(*err),
+            Err(err) => APIError::Transport(err),
+        }
+    }
 }
 
 impl fmt::Display for APIError {
@@ -18,6 +53,9 @@ impl fmt::Display for APIError {
             APIError::BadRequest(status, body) => {
                 write!(f, "Bad request ({}): {}", status, body)
             }
+            APIError::Transport(err) => write!(f, "Transport error: {}", err),
+            APIError::Secret(err) => write!(f, "API key error: {}", err),
+            APIError::InvalidPath(path) => write!(f, "Invalid id in request path: {}", path),
         }
     }
 }
@@ -35,3 +73,119 @@ impl From<serde_json::Error> for APIError {
         APIError::Serialization(err)
     }
 }
+
+impl From<SecretError> for APIError {
+    fn from(err: SecretError) -> Self {
+        APIError::Secret(err)
+    }
+}
+
+/// Error from waiting for an object to reach a state, e.g.
+/// [`crate::withdrawal::PendingWithdrawal::wait_until_final`].
+#[derive(Debug)]
+pub enum WaitError<T> {
+    Api(APIError),
+    /// The timeout elapsed; carries the last state seen.
+    Timeout(T),
+    /// The object expired first, e.g. an invoice that was never paid.
+    Expired(T),
+}
+
+impl<T> fmt::Display for WaitError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WaitError::Api(err) => write!(f, "{}", err),
+            WaitError::Timeout(_) => write!(f, "Timed out waiting for a final state"),
+            WaitError::Expired(_) => write!(f, "Expired before reaching the expected state"),
+        }
+    }
+}
+
+impl<T: fmt::Debug> std::error::Error for WaitError<T> {}
+
+impl<T> From<APIError> for WaitError<T> {
+    fn from(err: APIError) -> Self {
+        WaitError::Api(err)
+    }
+}
+
+/// An API key's scopes don't match how the client is used.
+#[derive(Debug)]
+pub enum ScopeError {
+    Api(APIError),
+    /// The key lacks a scope the caller requires.
+    Missing(ApiKeyScope),
+    /// The key can do more than the client is meant to, e.g. a
+    /// [`crate::ReadOnlyClient`] holding a key that may withdraw.
+    Excess(Vec<ApiKeyScope>),
+}
+
+impl fmt::Display for ScopeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScopeError::Api(err) => write!(f, "{}", err),
+            ScopeError::Missing(scope) => write!(f, "API key lacks the {:?} scope", scope),
+            ScopeError::Excess(scopes) => {
+                write!(f, "API key has more scopes than needed: {:?}", scopes)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ScopeError {}
+
+impl From<APIError> for ScopeError {
+    fn from(err: APIError) -> Self {
+        ScopeError::Api(err)
+    }
+}
+
+/// A value doesn't belong to the network a [`crate::network::NetworkClient`]
+/// is bound to.
+#[derive(Debug)]
+pub enum NetworkError {
+    Api(APIError),
+    /// Neither an address, an invoice nor a lightning address.
+    InvalidDestination(String),
+    /// A destination on another network, e.g. a testnet address given to a
+    /// mainnet client.
+    WrongNetwork {
+        expected: &'static str,
+        destination: String,
+    },
+    /// The API reports another network than the client's type.
+    Mismatch {
+        expected: &'static str,
+        actual: String,
+    },
+}
+
+impl fmt::Display for NetworkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NetworkError::Api(err) => write!(f, "{}", err),
+            NetworkError::InvalidDestination(destination) => {
+                write!(f, "Invalid destination: {}", destination)
+            }
+            NetworkError::WrongNetwork {
+                expected,
+                destination,
+            } => write!(f, "Destination {} is not on {}", destination, expected),
+            NetworkError::Mismatch { expected, actual } => {
+                write!(
+                    f,
+                    "Expected a {} account, but the API is on {}",
+                    expected, actual
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for NetworkError {}
+
+impl From<APIError> for NetworkError {
+    fn from(err: APIError) -> Self {
+        NetworkError::Api(err)
+    }
+}
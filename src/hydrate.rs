@@ -0,0 +1,345 @@
+//! Enriching transaction rows with the deposit or withdrawal behind them.
+//!
+//! [`Transaction`] only carries the ledger fields. Metadata, chain
+//! transaction ids, lightning payments and invoice ids live on the deposit or
+//! withdrawal, which [`Hydrator`] fetches concurrently:
+//!
+//! ```ignore
+//! let hydrator = client.hydrator().with_concurrency(16);
+//! let mut rows = Box::pin(hydrator.hydrate_stream(client.transactions_stream(query)));
+//! while let Some(row) = rows.next().await {
+//!     let row = row?;
+//!     write_report_row(&row.transaction, row.metadata(), row.chain_tx_id())?;
+//! }
+//! ```
+
+use crate::client::BitvoraClient;
+use crate::errors::APIError;
+use crate::models::{GetDepositData, PaymentQuery, Transaction, WithdrawData};
+use futures_util::{stream, Stream, StreamExt, TryStreamExt};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+pub const DEFAULT_CONCURRENCY: usize = 8;
+pub const DEFAULT_CACHE_CAPACITY: usize = 10_000;
+
+/// A transaction together with its full deposit or withdrawal record.
+#[derive(Debug, Clone)]
+pub struct HydratedTransaction {
+    pub transaction: Transaction,
+    pub details: TransactionDetails,
+}
+
+#[derive(Debug, Clone)]
+pub enum TransactionDetails {
+    Deposit(Box<GetDepositData>),
+    Withdrawal(Box<WithdrawData>),
+    /// A transaction type with no detail endpoint, or a transaction whose
+    /// deposit or withdrawal wasn't found.
+    None,
+}
+
+impl HydratedTransaction {
+    pub fn metadata(&self) -> Option<&HashMap<String, String>> {
+        match &self.details {
+            TransactionDetails::Deposit(deposit) => deposit.metadata.as_ref(),
+            TransactionDetails::Withdrawal(withdrawal) => withdrawal.metadata.as_ref(),
+            TransactionDetails::None => None,
+        }
+    }
+
+    pub fn chain_tx_id(&self) -> Option<&str> {
+        match &self.details {
+            TransactionDetails::Deposit(deposit) => deposit.chain_tx_id.as_deref(),
+            TransactionDetails::Withdrawal(withdrawal) => withdrawal.chain_tx_id.as_deref(),
+            TransactionDetails::None => None,
+        }
+    }
+}
+
+/// Fetches the details of transactions, a bounded number at a time.
+///
+/// Details are cached by transaction id and status, so a transaction seen
+/// again is only re-fetched once its status has changed. Clones share the
+/// cache.
+#[derive(Debug, Clone)]
+pub struct Hydrator {
+    client: BitvoraClient,
+    concurrency: usize,
+    cache: Arc<Mutex<Cache>>,
+}
+
+impl Hydrator {
+    pub fn new(client: BitvoraClient) -> Self {
+        Self {
+            client,
+            concurrency: DEFAULT_CONCURRENCY,
+            cache: Arc::new(Mutex::new(Cache::new(DEFAULT_CACHE_CAPACITY))),
+        }
+    }
+
+    /// Maximum number of detail requests in flight. Defaults to 8.
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Maximum number of records cached, evicting the oldest first. Defaults
+    /// to 10,000; 0 disables caching.
+    pub fn with_cache_capacity(mut self, capacity: usize) -> Self {
+        self.cache = Arc::new(Mutex::new(Cache::new(capacity)));
+        self
+    }
+
+    pub async fn hydrate(&self, transaction: Transaction) -> Result<HydratedTransaction, APIError> {
+        let key = (transaction.id.clone(), transaction.status.clone());
+        let cached = self.cache.lock().unwrap().get(&key).cloned();
+        let details = match cached {
+            Some(details) => details,
+            None => {
+                let details = self.fetch(&transaction).await?;
+                self.cache.lock().unwrap().insert(key, &details);
+                details
+            }
+        };
+        Ok(HydratedTransaction {
+            transaction,
+            details,
+        })
+    }
+
+    /// Hydrates a list of transactions, keeping their order. Fails on the
+    /// first error.
+    pub async fn hydrate_all(
+        &self,
+        transactions: Vec<Transaction>,
+    ) -> Result<Vec<HydratedTransaction>, APIError> {
+        stream::iter(transactions)
+            .map(|transaction| self.hydrate(transaction))
+            .buffered(self.concurrency)
+            .try_collect()
+            .await
+    }
+
+    /// Hydrates a stream of transactions, such as
+    /// [`BitvoraClient::transactions_stream`], keeping their order. Errors
+    /// from the input are passed through.
+    pub fn hydrate_stream(
+        self,
+        transactions: impl Stream<Item = Result<Transaction, APIError>> + Send,
+    ) -> impl Stream<Item = Result<HydratedTransaction, APIError>> + Send {
+        let concurrency = self.concurrency;
+        transactions
+            .map(move |transaction| {
+                let hydrator = self.clone();
+                async move { hydrator.hydrate(transaction?).await }
+            })
+            .buffered(concurrency)
+    }
+
+    async fn fetch(&self, transaction: &Transaction) -> Result<TransactionDetails, APIError> {
+        fetch_details(&self.client, transaction).await
+    }
+}
+
+/// Looks up the deposit or withdrawal behind a transaction.
+///
+/// A transaction's `id` is its ledger id, which differs from the id of the
+/// deposit or withdrawal, so the record is listed by `ledger_tx_id` and
+/// checked against it. Withdrawals that don't carry their ledger id can't be
+/// told apart from unrelated ones, so they are never matched.
+pub(crate) async fn fetch_details(
+    client: &BitvoraClient,
+    transaction: &Transaction,
+) -> Result<TransactionDetails, APIError> {
+    let query = PaymentQuery {
+        ledger_tx_id: Some(transaction.id.clone()),
+        ..Default::default()
+    };
+    Ok(match transaction.r#type.as_str() {
+        "deposit" => client
+            .list_deposits(query)
+            .await?
+            .data
+            .into_iter()
+            .find(|deposit| deposit.ledger_tx_id == transaction.id)
+            .map_or(TransactionDetails::None, |deposit| {
+                TransactionDetails::Deposit(Box::new(deposit))
+            }),
+        "withdrawal" => client
+            .list_withdrawals(query)
+            .await?
+            .data
+            .into_iter()
+            .find(|withdrawal| withdrawal.ledger_tx_id.as_deref() == Some(&transaction.id))
+            .map_or(TransactionDetails::None, |withdrawal| {
+                TransactionDetails::Withdrawal(Box::new(withdrawal))
+            }),
+        _ => TransactionDetails::None,
+    })
+}
+
+/// Deposit or withdrawal records by transaction id and status.
+#[derive(Debug)]
+struct Cache {
+    capacity: usize,
+    details: HashMap<(String, String), TransactionDetails>,
+    order: VecDeque<(String, String)>,
+}
+
+impl Cache {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            details: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    fn get(&self, key: &(String, String)) -> Option<&TransactionDetails> {
+        self.details.get(key)
+    }
+
+    fn insert(&mut self, key: (String, String), details: &TransactionDetails) {
+        if self.capacity == 0 || self.details.contains_key(&key) {
+            return;
+        }
+        if self.details.len() >= self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.details.remove(&oldest);
+            }
+        }
+        self.order.push_back(key.clone());
+        self.details.insert(key, details.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{self, with, Reply, Requests};
+    use serde_json::json;
+    use std::time::Duration;
+
+    /// Lists the deposit `dep_<ledger id>` or withdrawal `wd_<ledger id>`
+    /// recorded as the `ledger_tx_id` asked for, after a short delay.
+    fn client() -> (BitvoraClient, Requests) {
+        test_support::client(|request| {
+            let ledger_id = request.param("ledger_tx_id").unwrap();
+            let data = if request.path() == "/v1/transactions/withdrawals" {
+                let fields = json!({
+                    "ledger_tx_id": ledger_id,
+                    "chain_tx_id": format!("chain_{ledger_id}"),
+                    "metadata": {"order": ledger_id},
+                });
+                with(
+                    test_support::withdrawal(&format!("wd_{ledger_id}"), "completed"),
+                    fields,
+                )
+            } else {
+                assert_eq!(request.path(), "/v1/transactions/deposits");
+                let fields = json!({
+                    "ledger_tx_id": ledger_id,
+                    "metadata": {"order": ledger_id},
+                    "lightning_invoice_id": format!("inv_{ledger_id}"),
+                });
+                with(
+                    test_support::deposit(&format!("dep_{ledger_id}"), "completed"),
+                    fields,
+                )
+            };
+            Reply::ok(json!([data])).after(Duration::from_millis(5))
+        })
+    }
+
+    fn transaction(id: &str, kind: &str, status: &str) -> Transaction {
+        serde_json::from_value(test_support::transaction(id, kind, status)).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_hydrates_in_order_within_concurrency_limit() {
+        let (client, requests) = client();
+        let transactions: Vec<_> = (0..10)
+            .map(|i| {
+                let kind = if i % 2 == 0 { "deposit" } else { "withdrawal" };
+                transaction(&format!("t{i}"), kind, "completed")
+            })
+            .collect();
+
+        let hydrated = client
+            .hydrator()
+            .with_concurrency(3)
+            .hydrate_all(transactions)
+            .await
+            .unwrap();
+
+        assert_eq!(hydrated.len(), 10);
+        assert_eq!(hydrated[3].transaction.id, "t3");
+        assert_eq!(hydrated[3].chain_tx_id(), Some("chain_t3"));
+        assert_eq!(hydrated[4].metadata().unwrap()["order"], "t4");
+        assert!(matches!(
+            &hydrated[4].details,
+            TransactionDetails::Deposit(deposit)
+                if deposit.id == "dep_t4" && deposit.lightning_invoice_id.as_deref() == Some("inv_t4")
+        ));
+        assert!(matches!(
+            &hydrated[3].details,
+            TransactionDetails::Withdrawal(withdrawal) if withdrawal.id == "wd_t3"
+        ));
+        assert_eq!(
+            requests.targets()[..2],
+            [
+                "/v1/transactions/deposits?ledger_tx_id=t0",
+                "/v1/transactions/withdrawals?ledger_tx_id=t1",
+            ]
+        );
+        assert_eq!(requests.len(), 10);
+        assert_eq!(requests.peak_in_flight(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_caches_until_status_changes() {
+        let (client, requests) = client();
+        // One at a time, so the repeated row finds the first one cached.
+        let hydrator = client.hydrator().with_concurrency(1);
+        let rows = stream::iter([
+            Ok(transaction("t1", "deposit", "pending")),
+            Ok(transaction("t1", "deposit", "pending")),
+            Ok(transaction("t2", "fee", "completed")),
+            Ok(transaction("t1", "deposit", "completed")),
+        ]);
+
+        let hydrated: Vec<_> = hydrator.hydrate_stream(rows).try_collect().await.unwrap();
+
+        assert!(matches!(hydrated[2].details, TransactionDetails::None));
+        assert_eq!(hydrated[3].transaction.status, "completed");
+        assert_eq!(requests.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_records_of_other_ledger_transactions_are_ignored() {
+        // An API that ignores the filter and lists unrelated records. The
+        // withdrawal carries no ledger id, so it can't be matched even as the
+        // only result.
+        let (client, _) = test_support::client(|request| {
+            let data = if request.path() == "/v1/transactions/withdrawals" {
+                json!([test_support::withdrawal("w1", "completed")])
+            } else {
+                json!([test_support::deposit("d1", "completed")])
+            };
+            Reply::ok(data)
+        });
+
+        let hydrated = client
+            .hydrator()
+            .hydrate_all(vec![
+                transaction("t1", "deposit", "completed"),
+                transaction("t2", "withdrawal", "completed"),
+            ])
+            .await
+            .unwrap();
+
+        assert!(matches!(hydrated[0].details, TransactionDetails::None));
+        assert!(matches!(hydrated[1].details, TransactionDetails::None));
+    }
+}
@@ -0,0 +1,313 @@
+//! Waiting for a lightning invoice to be paid.
+//!
+//! ```ignore
+//! let mut invoice = client.create_lightning_invoice_and_track(request).await?;
+//! show_qr(&invoice.latest().unwrap().payment_request);
+//! match invoice.wait_paid().await {
+//!     Ok(paid) => fulfil_order(paid.id),
+//!     Err(WaitError::Expired(_)) => cancel_order(),
+//!     Err(err) => return Err(err.into()),
+//! }
+//! ```
+
+use crate::client::BitvoraClient;
+use crate::errors::{APIError, WaitError};
+use crate::models::CreateLightningInvoiceData;
+use crate::wait::{wait_for, Backoff, Woken};
+use crate::webhooks::WebhookEvent;
+use futures_util::Stream;
+use std::time::{Duration, SystemTime};
+use tokio::sync::broadcast;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InvoiceStatus {
+    Open,
+    Paid,
+    Expired,
+}
+
+/// Handle to a lightning invoice that may not have been paid yet.
+#[derive(Debug)]
+pub struct PendingInvoice {
+    client: BitvoraClient,
+    id: String,
+    latest: Option<CreateLightningInvoiceData>,
+    expires_at: Option<SystemTime>,
+    backoff: Backoff,
+    events: Option<broadcast::Receiver<WebhookEvent>>,
+}
+
+impl PendingInvoice {
+    pub fn new(client: BitvoraClient, id: impl Into<String>) -> Self {
+        Self {
+            client,
+            id: id.into(),
+            latest: None,
+            expires_at: None,
+            backoff: Backoff::default(),
+            events: None,
+        }
+    }
+
+    pub(crate) fn from_data(client: BitvoraClient, data: CreateLightningInvoiceData) -> Self {
+        let mut pending = Self::new(client, data.id.clone());
+        pending.latest = Some(data);
+        pending
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// The most recent state fetched, if any.
+    pub fn latest(&self) -> Option<&CreateLightningInvoiceData> {
+        self.latest.as_ref()
+    }
+
+    /// When the invoice expires. Set automatically for invoices created with
+    /// [`BitvoraClient::create_lightning_invoice_and_track`]; without it,
+    /// waits only end when the invoice is paid.
+    pub fn with_expiry(mut self, expires_at: SystemTime) -> Self {
+        self.expires_at = Some(expires_at);
+        self
+    }
+
+    /// Delay before the first re-check, doubling up to `max`. Defaults to 1s
+    /// and 30s.
+    pub fn with_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.backoff = Backoff { initial, max };
+        self
+    }
+
+    /// Also listens for webhook events, so a `deposit.lightning.completed`
+    /// event for this invoice is noticed right away instead of at the next
+    /// poll.
+    pub fn with_events(mut self, events: broadcast::Receiver<WebhookEvent>) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    pub fn is_paid(&self) -> bool {
+        self.latest.as_ref().is_some_and(|data| data.settled)
+    }
+
+    /// Whether the expiry has passed without the invoice being paid, as far
+    /// as the last fetched state shows.
+    pub fn is_expired(&self) -> bool {
+        !self.is_paid()
+            && self
+                .expires_at
+                .is_some_and(|expires_at| SystemTime::now() >= expires_at)
+    }
+
+    pub fn status(&self) -> InvoiceStatus {
+        if self.is_paid() {
+            InvoiceStatus::Paid
+        } else if self.is_expired() {
+            InvoiceStatus::Expired
+        } else {
+            InvoiceStatus::Open
+        }
+    }
+
+    /// Resolves once the invoice is paid, or fails with
+    /// [`WaitError::Expired`] when it expires first.
+    pub async fn wait_paid(
+        &mut self,
+    ) -> Result<CreateLightningInvoiceData, WaitError<CreateLightningInvoiceData>> {
+        if let Some(latest) = self.latest.as_ref().filter(|data| data.settled) {
+            return Ok(latest.clone());
+        }
+
+        match self.wait_settled().await {
+            Ok(data) if data.settled => Ok(data),
+            Ok(data) | Err(WaitError::Timeout(data)) => Err(WaitError::Expired(data)),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Yields each new status, starting with the current one, and ends after
+    /// [`InvoiceStatus::Paid`] or [`InvoiceStatus::Expired`].
+    pub fn status_stream(self) -> impl Stream<Item = Result<InvoiceStatus, APIError>> + Send {
+        futures_util::stream::unfold(
+            (self, None::<InvoiceStatus>),
+            |(mut invoice, last)| async move {
+                let next = match last {
+                    None => invoice.refresh().await.map(|()| invoice.status()),
+                    Some(InvoiceStatus::Paid | InvoiceStatus::Expired) => return None,
+                    Some(InvoiceStatus::Open) => {
+                        match invoice.wait_settled().await {
+                            Ok(_) => Ok(invoice.status()),
+                            // The deadline is the expiry, so don't rely on the
+                            // clock having passed it to the microsecond.
+                            Err(WaitError::Timeout(_) | WaitError::Expired(_)) => {
+                                Ok(if invoice.is_paid() {
+                                    InvoiceStatus::Paid
+                                } else {
+                                    InvoiceStatus::Expired
+                                })
+                            }
+                            Err(WaitError::Api(err)) => Err(err),
+                        }
+                    }
+                };
+                let last = match &next {
+                    Ok(status) => Some(*status),
+                    Err(_) => {
+                        tokio::time::sleep(invoice.backoff.initial).await;
+                        last
+                    }
+                };
+                Some((next, (invoice, last)))
+            },
+        )
+    }
+
+    async fn refresh(&mut self) -> Result<(), APIError> {
+        self.latest = Some(self.client.get_lightning_invoice(&self.id).await?.data);
+        Ok(())
+    }
+
+    /// Polls until the invoice is settled or expires.
+    async fn wait_settled(
+        &mut self,
+    ) -> Result<CreateLightningInvoiceData, WaitError<CreateLightningInvoiceData>> {
+        let timeout = match self.expires_at {
+            Some(expires_at) => expires_at
+                .duration_since(SystemTime::now())
+                .unwrap_or_default(),
+            None => Duration::MAX,
+        };
+        let client = &self.client;
+        let id = &self.id;
+        let result = wait_for(
+            timeout,
+            self.backoff,
+            || async move { Ok(client.get_lightning_invoice(id).await?.data) },
+            |data| data.settled,
+            self.events.as_mut(),
+            |event| match event {
+                WebhookEvent::DepositLightningCompleted(deposit)
+                    if deposit.lightning_invoice_id.as_deref() == Some(id.as_str()) =>
+                {
+                    Some(Woken::Recheck)
+                }
+                _ => None,
+            },
+        )
+        .await;
+
+        match &result {
+            Ok(data) | Err(WaitError::Timeout(data)) => self.latest = Some(data.clone()),
+            Err(WaitError::Api(_)) | Err(WaitError::Expired(_)) => {}
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::CreateLightningInvoiceRequest;
+    use crate::test_support::{self, Reply, Requests};
+    use futures_util::StreamExt;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// A client whose `get_lightning_invoice` reports the invoice as settled
+    /// from call `settled_from` on.
+    fn client(settled_from: usize) -> (BitvoraClient, Requests) {
+        let calls = Arc::new(AtomicUsize::new(0));
+        test_support::client(move |request| {
+            assert_eq!(request.path(), "/v1/bitcoin/deposit/lightning-invoice/i1");
+            let settled = calls.fetch_add(1, Ordering::SeqCst) + 1 >= settled_from;
+            Reply::ok(test_support::lightning_invoice("i1", settled))
+        })
+    }
+
+    fn fast(invoice: PendingInvoice) -> PendingInvoice {
+        invoice.with_backoff(Duration::from_millis(1), Duration::from_millis(5))
+    }
+
+    #[tokio::test]
+    async fn test_wait_paid_resolves_when_settled() {
+        let (client, requests) = client(3);
+        let mut invoice = fast(client.pending_invoice("i1"));
+
+        let paid = invoice.wait_paid().await.unwrap();
+
+        assert!(paid.settled);
+        assert!(invoice.is_paid());
+        assert_eq!(requests.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_wait_paid_fails_on_expiry() {
+        let (client, _) = client(usize::MAX);
+        let mut invoice = fast(client.pending_invoice("i1"))
+            .with_expiry(SystemTime::now() + Duration::from_millis(20));
+
+        assert!(!invoice.is_expired());
+        let result = invoice.wait_paid().await;
+
+        assert!(matches!(result, Err(WaitError::Expired(_))));
+        assert!(invoice.is_expired());
+        assert_eq!(invoice.status(), InvoiceStatus::Expired);
+    }
+
+    #[tokio::test]
+    async fn test_unrepresentable_expiry_tracks_without_one() {
+        let (client, _) =
+            test_support::client(|_| Reply::ok(test_support::lightning_invoice("i1", false)));
+
+        let invoice = client
+            .create_lightning_invoice_and_track(CreateLightningInvoiceRequest {
+                amount: 21.0,
+                currency: "sats".to_string(),
+                description: "coffee".to_string(),
+                expiry_seconds: u64::MAX,
+                metadata: None,
+            })
+            .await
+            .unwrap();
+
+        assert!(!invoice.is_expired());
+    }
+
+    #[tokio::test]
+    async fn test_status_stream_reports_changes() {
+        let (client, _) = client(2);
+        let statuses: Vec<_> = fast(client.pending_invoice("i1"))
+            .status_stream()
+            .map(Result::unwrap)
+            .collect()
+            .await;
+
+        assert_eq!(statuses, vec![InvoiceStatus::Open, InvoiceStatus::Paid]);
+    }
+
+    #[tokio::test]
+    async fn test_deposit_webhook_triggers_recheck() {
+        let (client, requests) = client(2);
+        let (sender, receiver) = broadcast::channel(8);
+        let mut invoice = client
+            .pending_invoice("i1")
+            .with_backoff(Duration::from_secs(60), Duration::from_secs(60))
+            .with_events(receiver);
+
+        let body = br#"{"event":"deposit.lightning.completed","data":{"id":"d1","ledger_tx_id":"l1","recipient":"r","amount_sats":21,"fee_sats":0.0,"chain_tx_id":null,"rail_type":"lightning","network_type":"signet","status":"completed","metadata":null,"lightning_invoice_id":"i1","created_at":"2024-01-01T00:00:00Z"}}"#;
+        let event = WebhookEvent::from_slice(body).unwrap();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            sender.send(event).unwrap();
+        });
+
+        let paid = tokio::time::timeout(Duration::from_secs(5), invoice.wait_paid())
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert!(paid.settled);
+        assert_eq!(requests.len(), 2);
+    }
+}
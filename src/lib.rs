@@ -1,7 +1,34 @@
 pub mod client;
 pub mod models;
 pub mod errors;
+pub mod address;
+#[cfg(feature = "blocking")]
+pub mod blocking;
+pub mod debug;
+pub mod hydrate;
+pub mod invoice;
+pub mod network;
+pub mod poller;
+pub mod read_only;
+pub mod secret;
+pub mod service;
+#[cfg(feature = "metrics")]
+pub mod telemetry;
+#[cfg(test)]
+mod test_support;
+#[cfg(feature = "tracing")]
+pub mod trace;
+pub mod transactions;
+mod wait;
+pub mod webhooks;
+pub mod withdrawal;
 
 pub use client::BitvoraClient;
+pub use errors::{APIError, NetworkError, ScopeError, WaitError};
+pub use invoice::PendingInvoice;
 pub use models::*;
-pub use errors::APIError;
+pub use read_only::{PaymentsClient, ReadOnlyClient};
+pub use secret::{ApiKey, SecretProvider};
+pub use service::Operation;
+pub use webhooks::{WebhookEvent, WebhookVerifier};
+pub use withdrawal::PendingWithdrawal;
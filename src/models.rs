@@ -1,5 +1,6 @@
+use crate::secret::ApiKey;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 #[derive(Serialize)]
 pub struct WithdrawRequest {
@@ -16,9 +17,13 @@ pub struct WithdrawResponse {
     pub data: WithdrawData,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct WithdrawData {
     pub id: String,
+    /// Id of the ledger [`Transaction`] for this withdrawal. Not every
+    /// response includes it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ledger_tx_id: Option<String>,
     pub amount_sats: u64,
     pub recipient: String,
     pub fee_sats: f64,
@@ -70,7 +75,7 @@ pub struct CreateLightningInvoiceResponse {
     pub data: CreateLightningInvoiceData,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Clone)]
 pub struct CreateLightningInvoiceData {
     pub id: String,
     pub node_id: String,
@@ -84,6 +89,44 @@ pub struct CreateLightningInvoiceData {
     pub lightning_address_id: Option<String>,
 }
 
+/// Filters and paging for listing lightning invoices. Fields left as `None`
+/// are not sent.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LightningInvoiceQuery {
+    pub limit: Option<u32>,
+    pub cursor: Option<String>,
+    pub offset: Option<u64>,
+    pub settled: Option<bool>,
+    pub expired: Option<bool>,
+    /// Only invoices created for this lightning address.
+    pub lightning_address_id: Option<String>,
+    /// Only invoices whose metadata has all of these key/value pairs.
+    pub metadata: BTreeMap<String, String>,
+}
+
+impl LightningInvoiceQuery {
+    /// Query string pairs, with metadata filters sent as `metadata[key]`.
+    pub fn to_pairs(&self) -> Vec<(String, String)> {
+        QueryPairs::default()
+            .push("limit", self.limit)
+            .push("cursor", self.cursor.as_ref())
+            .push("offset", self.offset)
+            .push("settled", self.settled)
+            .push("expired", self.expired)
+            .push("lightning_address_id", self.lightning_address_id.as_ref())
+            .metadata(&self.metadata)
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ListLightningInvoicesResponse {
+    pub status: u16,
+    pub message: String,
+    pub data: Vec<CreateLightningInvoiceData>,
+    #[serde(default)]
+    pub pagination: Pagination,
+}
+
 #[derive(Serialize)]
 pub struct CreateLightningAddressRequest {
     pub handle: String,
@@ -110,6 +153,83 @@ pub struct CreateLightningAddressData {
     pub deleted_at: Option<String>,
 }
 
+#[derive(Deserialize, Debug)]
+pub struct ListLightningAddressesResponse {
+    pub status: u16,
+    pub message: String,
+    pub data: Vec<CreateLightningAddressData>,
+}
+
+/// Replaces the address's metadata as a whole.
+#[derive(Serialize)]
+pub struct UpdateLightningAddressRequest {
+    pub metadata: HashMap<String, String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct DeleteLightningAddressResponse {
+    pub status: u16,
+    pub message: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ListDepositsResponse {
+    pub status: u16,
+    pub message: String,
+    pub data: Vec<GetDepositData>,
+    #[serde(default)]
+    pub pagination: Pagination,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ListWithdrawalsResponse {
+    pub status: u16,
+    pub message: String,
+    pub data: Vec<WithdrawData>,
+    #[serde(default)]
+    pub pagination: Pagination,
+}
+
+/// Filters and paging for listing withdrawals or deposits. Fields left as
+/// `None` are not sent.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PaymentQuery {
+    pub limit: Option<u32>,
+    pub cursor: Option<String>,
+    pub offset: Option<u64>,
+    pub status: Option<String>,
+    /// `"lightning"` or `"onchain"`.
+    pub rail_type: Option<String>,
+    /// Only payments created at or after this RFC 3339 timestamp.
+    pub created_after: Option<String>,
+    /// Only payments created before this RFC 3339 timestamp.
+    pub created_before: Option<String>,
+    /// Destination of a withdrawal, or the address a deposit was paid to.
+    pub recipient: Option<String>,
+    /// Only the payment recorded as this ledger transaction, the `id` of a
+    /// [`Transaction`].
+    pub ledger_tx_id: Option<String>,
+    /// Only payments whose metadata has all of these key/value pairs.
+    pub metadata: BTreeMap<String, String>,
+}
+
+impl PaymentQuery {
+    /// Query string pairs, with metadata filters sent as `metadata[key]`.
+    pub fn to_pairs(&self) -> Vec<(String, String)> {
+        QueryPairs::default()
+            .push("limit", self.limit)
+            .push("cursor", self.cursor.as_ref())
+            .push("offset", self.offset)
+            .push("status", self.status.as_ref())
+            .push("rail_type", self.rail_type.as_ref())
+            .push("created_after", self.created_after.as_ref())
+            .push("created_before", self.created_before.as_ref())
+            .push("recipient", self.recipient.as_ref())
+            .push("ledger_tx_id", self.ledger_tx_id.as_ref())
+            .metadata(&self.metadata)
+    }
+}
+
 #[derive(Serialize)]
 pub struct CreateOnChainAddressRequest {
     pub metadata: Option<HashMap<String, String>>,
@@ -122,7 +242,7 @@ pub struct CreateOnChainAddressResponse {
     pub data: CreateOnChainAddressData,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Clone)]
 pub struct CreateOnChainAddressData {
     pub id: String,
     pub address: String,
@@ -130,6 +250,13 @@ pub struct CreateOnChainAddressData {
     pub created_at: String,
 }
 
+#[derive(Deserialize, Debug)]
+pub struct ListOnChainAddressesResponse {
+    pub status: u16,
+    pub message: String,
+    pub data: Vec<CreateOnChainAddressData>,
+}
+
 #[derive(Deserialize, Debug)]
 pub struct GetDepositResponse {
     pub status: u16,
@@ -137,7 +264,7 @@ pub struct GetDepositResponse {
     pub data: GetDepositData,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct GetDepositData {
     pub id: String,
     pub ledger_tx_id: String,
@@ -165,7 +292,7 @@ pub struct GetBalanceData {
     pub balance: u32,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LNDTrackPaymentResponse {
     pub payment_hash: String,
     pub value: String,
@@ -184,7 +311,7 @@ pub struct LNDTrackPaymentResponse {
     pub failure_reason: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LNDHTLCAttempt {
     pub attempt_id: String,
     pub status: String,
@@ -195,7 +322,7 @@ pub struct LNDHTLCAttempt {
     pub preimage: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LNDPaymentRoute {
     pub total_time_lock: i32,
     pub total_fees: String,
@@ -204,7 +331,7 @@ pub struct LNDPaymentRoute {
     pub hops: Vec<LNDHop>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LNDHop {
     pub chan_id: String,
     pub chan_capacity: String,
@@ -212,7 +339,7 @@ pub struct LNDHop {
     pub expiry: i32,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LNDPaymentFailure {
     pub code: String,
     pub channel_update: LNDChannelUpdate,
@@ -224,7 +351,7 @@ pub struct LNDPaymentFailure {
     pub height: i32,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LNDChannelUpdate {
     pub signature: String,
     pub chain_hash: String,
@@ -247,7 +374,7 @@ pub struct GetTransactionsResponse {
     pub data: Vec<Transaction>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Transaction {
     pub id: String,
     pub company_id: String,
@@ -259,3 +386,260 @@ pub struct Transaction {
     pub status: String,
     pub created_at: String,
 }
+
+/// Filters and paging for listing transactions. Fields left as `None` are
+/// not sent.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TransactionQuery {
+    /// Maximum number of transactions per page.
+    pub limit: Option<u32>,
+    /// Opaque cursor from [`Pagination::next_cursor`] of the previous page.
+    pub cursor: Option<String>,
+    /// Number of transactions to skip, for APIs that page by offset.
+    pub offset: Option<u64>,
+    /// Only transactions created at or after this RFC 3339 timestamp.
+    pub created_after: Option<String>,
+    /// Only transactions created before this RFC 3339 timestamp.
+    pub created_before: Option<String>,
+    /// `"deposit"` or `"withdrawal"`.
+    pub r#type: Option<String>,
+    /// `"lightning"` or `"onchain"`.
+    pub rail_type: Option<String>,
+    pub status: Option<String>,
+    /// Only transactions whose metadata has all of these key/value pairs.
+    pub metadata: BTreeMap<String, String>,
+}
+
+impl TransactionQuery {
+    /// Query string pairs, with metadata filters sent as `metadata[key]`.
+    pub fn to_pairs(&self) -> Vec<(String, String)> {
+        QueryPairs::default()
+            .push("limit", self.limit)
+            .push("cursor", self.cursor.as_ref())
+            .push("offset", self.offset)
+            .push("created_after", self.created_after.as_ref())
+            .push("created_before", self.created_before.as_ref())
+            .push("type", self.r#type.as_ref())
+            .push("rail_type", self.rail_type.as_ref())
+            .push("status", self.status.as_ref())
+            .metadata(&self.metadata)
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ListTransactionsResponse {
+    pub status: u16,
+    pub message: String,
+    pub data: Vec<Transaction>,
+    /// Missing when the API returned every matching transaction at once.
+    #[serde(default)]
+    pub pagination: Pagination,
+}
+
+impl ListTransactionsResponse {
+    /// The query for the page after this one, or `None` on the last page.
+    /// Follows the cursor when the API returns one, and advances the offset
+    /// otherwise.
+    pub fn next_query(&self, query: &TransactionQuery) -> Option<TransactionQuery> {
+        if !self.pagination.has_more || self.data.is_empty() {
+            return None;
+        }
+        let mut next = query.clone();
+        match &self.pagination.next_cursor {
+            Some(cursor) => next.cursor = Some(cursor.clone()),
+            None => {
+                next.offset = Some(query.offset.unwrap_or(0) + self.data.len() as u64);
+            }
+        }
+        Some(next)
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Pagination {
+    #[serde(default)]
+    pub has_more: bool,
+    #[serde(default)]
+    pub next_cursor: Option<String>,
+    /// Number of transactions matching the filters, if the API counts them.
+    #[serde(default)]
+    pub total: Option<u64>,
+}
+
+#[derive(Serialize)]
+pub struct CreateWebhookRequest {
+    pub url: String,
+    /// Event types to deliver, e.g. `"withdrawal.completed"`.
+    pub events: Vec<String>,
+    pub description: Option<String>,
+}
+
+/// Fields left as `None` are not changed.
+#[derive(Serialize, Default)]
+pub struct UpdateWebhookRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub events: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+}
+
+#[derive(Serialize)]
+pub struct SendTestWebhookRequest {
+    pub event: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct WebhookResponse {
+    pub status: u16,
+    pub message: String,
+    pub data: WebhookData,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ListWebhooksResponse {
+    pub status: u16,
+    pub message: String,
+    pub data: Vec<WebhookData>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct WebhookData {
+    pub id: String,
+    pub url: String,
+    pub events: Vec<String>,
+    pub description: Option<String>,
+    pub enabled: bool,
+    /// Signing secret; only returned when the webhook is created or its secret
+    /// is rotated.
+    #[serde(default)]
+    pub secret: Option<ApiKey>,
+    pub created_at: String,
+    pub updated_at: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct DeleteWebhookResponse {
+    pub status: u16,
+    pub message: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct SendTestWebhookResponse {
+    pub status: u16,
+    pub message: String,
+    pub data: SendTestWebhookData,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct SendTestWebhookData {
+    pub delivered: bool,
+    /// Status code returned by the webhook URL, if it answered.
+    pub response_status: Option<u16>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct AccountResponse {
+    pub status: u16,
+    pub message: String,
+    pub data: AccountData,
+}
+
+/// The company the API key belongs to.
+#[derive(Deserialize, Debug, Clone)]
+pub struct AccountData {
+    /// Matches [`Transaction::company_id`].
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub email: Option<String>,
+    #[serde(default)]
+    pub network_type: Option<String>,
+    pub created_at: String,
+}
+
+/// What an API key is allowed to do.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiKeyScope {
+    /// Balances, transactions and lookups.
+    Read,
+    /// Creating invoices and deposit addresses.
+    Deposit,
+    /// Moving money out.
+    Withdraw,
+    ManageWebhooks,
+    ManageApiKeys,
+    /// A scope this version of the SDK doesn't know about.
+    #[serde(untagged)]
+    Other(String),
+}
+
+#[derive(Serialize)]
+pub struct CreateApiKeyRequest {
+    pub name: String,
+    pub scopes: Vec<ApiKeyScope>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ApiKeyResponse {
+    pub status: u16,
+    pub message: String,
+    pub data: ApiKeyData,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ListApiKeysResponse {
+    pub status: u16,
+    pub message: String,
+    pub data: Vec<ApiKeyData>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ApiKeyData {
+    pub id: String,
+    pub name: String,
+    pub scopes: Vec<ApiKeyScope>,
+    /// The key itself; only returned when it is created.
+    #[serde(default)]
+    pub key: Option<ApiKey>,
+    pub created_at: String,
+    pub last_used_at: Option<String>,
+    pub revoked_at: Option<String>,
+}
+
+impl ApiKeyData {
+    pub fn has_scope(&self, scope: &ApiKeyScope) -> bool {
+        self.scopes.contains(scope)
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct RevokeApiKeyResponse {
+    pub status: u16,
+    pub message: String,
+}
+
+/// Query string pairs of a listing, leaving out unset filters.
+#[derive(Default)]
+struct QueryPairs(Vec<(String, String)>);
+
+impl QueryPairs {
+    fn push(mut self, key: &str, value: Option<impl ToString>) -> Self {
+        if let Some(value) = value {
+            self.0.push((key.to_string(), value.to_string()));
+        }
+        self
+    }
+
+    /// Adds the metadata filters, sent as `metadata[key]`, last.
+    fn metadata(mut self, metadata: &BTreeMap<String, String>) -> Vec<(String, String)> {
+        for (key, value) in metadata {
+            self.0.push((format!("metadata[{}]", key), value.clone()));
+        }
+        self.0
+    }
+}
@@ -0,0 +1,513 @@
+//! The Bitcoin network encoded in the client type.
+//!
+//! A [`NetworkClient<Mainnet>`] and a [`NetworkClient<Signet>`] are different
+//! types, and so are the addresses, invoices, destinations and amounts they
+//! hand out, so a signet address can't be given to a mainnet client by
+//! mistake:
+//!
+//! ```ignore
+//! let signet = NetworkClient::<Signet>::new(&signet_key);
+//! let mainnet = NetworkClient::<Mainnet>::new(&mainnet_key);
+//!
+//! let address = signet.create_onchain_address(request).await?;
+//! mainnet.watch_onchain_address(&address); // does not compile
+//!
+//! let destination: Destination<Mainnet> = "bc1q...".parse()?;
+//! mainnet.withdraw(&destination, Sats::new(21_000), HashMap::new()).await?;
+//! ```
+//!
+//! Destinations are only checked by prefix; the API still validates them
+//! fully.
+
+use crate::address::AddressWatcher;
+use crate::errors::{APIError, NetworkError};
+use crate::invoice::PendingInvoice;
+use crate::models::{
+    CreateLightningInvoiceData, CreateLightningInvoiceRequest, CreateOnChainAddressData,
+    CreateOnChainAddressRequest, EstimateWithdrawalRequest, EstimateWithdrawalResponse,
+    WithdrawRequest, WithdrawResponse,
+};
+use crate::BitvoraClient;
+use std::collections::HashMap;
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::Deref;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// A network Bitvora runs on. Implemented by [`Mainnet`] and [`Signet`] only.
+pub trait Network: sealed::Sealed + fmt::Debug + Clone + Copy + Send + Sync + 'static {
+    /// `network_type` as reported by the API.
+    const NETWORK_TYPE: &'static str;
+    const BASE_URL: &'static str;
+    /// Leading characters of on-chain addresses, lowercase.
+    const ADDRESS_PREFIXES: &'static [&'static str];
+    /// Human-readable prefix of BOLT 11 invoices.
+    const INVOICE_PREFIX: &'static str;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Mainnet;
+
+/// Bitvora's test network.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Signet;
+
+pub type Testnet = Signet;
+
+impl sealed::Sealed for Mainnet {}
+impl sealed::Sealed for Signet {}
+
+impl Network for Mainnet {
+    const NETWORK_TYPE: &'static str = "mainnet";
+    const BASE_URL: &'static str = "https://api.bitvora.com";
+    const ADDRESS_PREFIXES: &'static [&'static str] = &["bc1", "1", "3"];
+    const INVOICE_PREFIX: &'static str = "lnbc";
+}
+
+impl Network for Signet {
+    const NETWORK_TYPE: &'static str = "signet";
+    const BASE_URL: &'static str = "https://api.signet.bitvora.com";
+    const ADDRESS_PREFIXES: &'static [&'static str] = &["tb1", "m", "n", "2"];
+    const INVOICE_PREFIX: &'static str = "lntbs";
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DestinationKind {
+    OnChain,
+    Bolt11,
+    /// A `user@domain` lightning address, which isn't tied to a network.
+    LightningAddress,
+}
+
+/// A withdrawal destination checked to belong to network `N`.
+pub struct Destination<N> {
+    value: String,
+    kind: DestinationKind,
+    network: PhantomData<N>,
+}
+
+impl<N: Network> Destination<N> {
+    pub fn parse(value: &str) -> Result<Self, NetworkError> {
+        let value = value.trim();
+        let lower = value.to_ascii_lowercase();
+        let kind = if value.contains('@') {
+            DestinationKind::LightningAddress
+        } else if lower.starts_with("ln") {
+            // The amount or the `1` separator follows the prefix, which tells
+            // `lnbc` apart from regtest's `lnbcrt`.
+            let valid = lower
+                .strip_prefix(N::INVOICE_PREFIX)
+                .is_some_and(|rest| rest.starts_with(|c: char| c.is_ascii_digit()));
+            if !valid {
+                return Err(NetworkError::WrongNetwork {
+                    expected: N::NETWORK_TYPE,
+                    destination: value.to_string(),
+                });
+            }
+            DestinationKind::Bolt11
+        } else if (26..=90).contains(&value.len()) {
+            if !N::ADDRESS_PREFIXES
+                .iter()
+                .any(|prefix| lower.starts_with(prefix))
+            {
+                return Err(NetworkError::WrongNetwork {
+                    expected: N::NETWORK_TYPE,
+                    destination: value.to_string(),
+                });
+            }
+            DestinationKind::OnChain
+        } else {
+            return Err(NetworkError::InvalidDestination(value.to_string()));
+        };
+        Ok(Self {
+            value: value.to_string(),
+            kind,
+            network: PhantomData,
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.value
+    }
+
+    pub fn kind(&self) -> DestinationKind {
+        self.kind
+    }
+}
+
+impl<N: Network> FromStr for Destination<N> {
+    type Err = NetworkError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Self::parse(value)
+    }
+}
+
+// Written out so `N` needn't implement the traits itself.
+impl<N> Clone for Destination<N> {
+    fn clone(&self) -> Self {
+        Self {
+            value: self.value.clone(),
+            kind: self.kind,
+            network: PhantomData,
+        }
+    }
+}
+
+impl<N: Network> fmt::Debug for Destination<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Destination<{}>({})", N::NETWORK_TYPE, self.value)
+    }
+}
+
+/// An amount of sats on network `N`.
+pub struct Sats<N> {
+    amount: u64,
+    network: PhantomData<N>,
+}
+
+impl<N> Sats<N> {
+    pub fn new(amount: u64) -> Self {
+        Self {
+            amount,
+            network: PhantomData,
+        }
+    }
+
+    pub fn amount(&self) -> u64 {
+        self.amount
+    }
+}
+
+impl<N> Clone for Sats<N> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<N> Copy for Sats<N> {}
+
+impl<N> PartialEq for Sats<N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.amount == other.amount
+    }
+}
+
+impl<N: Network> fmt::Debug for Sats<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Sats<{}>({})", N::NETWORK_TYPE, self.amount)
+    }
+}
+
+/// An on-chain deposit address created on network `N`.
+pub struct OnChainAddress<N> {
+    data: CreateOnChainAddressData,
+    network: PhantomData<N>,
+}
+
+impl<N> OnChainAddress<N> {
+    pub fn into_inner(self) -> CreateOnChainAddressData {
+        self.data
+    }
+}
+
+impl<N> Deref for OnChainAddress<N> {
+    type Target = CreateOnChainAddressData;
+
+    fn deref(&self) -> &Self::Target {
+        &self.data
+    }
+}
+
+impl<N: Network> fmt::Debug for OnChainAddress<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple(&format!("OnChainAddress<{}>", N::NETWORK_TYPE))
+            .field(&self.data)
+            .finish()
+    }
+}
+
+/// A lightning invoice created on network `N`.
+pub struct LightningInvoice<N> {
+    data: CreateLightningInvoiceData,
+    expires_at: Option<SystemTime>,
+    network: PhantomData<N>,
+}
+
+impl<N> LightningInvoice<N> {
+    /// When the invoice expires, or `None` if its expiry is too far away for
+    /// the system clock to represent.
+    pub fn expires_at(&self) -> Option<SystemTime> {
+        self.expires_at
+    }
+
+    pub fn into_inner(self) -> CreateLightningInvoiceData {
+        self.data
+    }
+}
+
+impl<N> Deref for LightningInvoice<N> {
+    type Target = CreateLightningInvoiceData;
+
+    fn deref(&self) -> &Self::Target {
+        &self.data
+    }
+}
+
+impl<N: Network> fmt::Debug for LightningInvoice<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple(&format!("LightningInvoice<{}>", N::NETWORK_TYPE))
+            .field(&self.data)
+            .finish()
+    }
+}
+
+/// A client bound to network `N`, whose inputs and outputs carry `N`.
+///
+/// ```compile_fail
+/// # use bitvora::network::{Mainnet, NetworkClient, OnChainAddress, Signet};
+/// fn watch(mainnet: NetworkClient<Mainnet>, address: OnChainAddress<Signet>) {
+///     mainnet.watch_onchain_address(&address);
+/// }
+/// ```
+pub struct NetworkClient<N> {
+    client: BitvoraClient,
+    network: PhantomData<N>,
+}
+
+impl<N: Network> NetworkClient<N> {
+    /// A client for the network's public API endpoint.
+    pub fn new(api_key: &str) -> Self {
+        Self::from_client(BitvoraClient::new(N::BASE_URL, api_key))
+    }
+
+    /// Binds an existing client, e.g. one with a custom transport, to `N`.
+    /// [`NetworkClient::verify_network`] checks that it really talks to `N`.
+    pub fn from_client(client: BitvoraClient) -> Self {
+        Self {
+            client,
+            network: PhantomData,
+        }
+    }
+
+    /// The untyped client, for endpoints without a network-aware wrapper.
+    pub fn client(&self) -> &BitvoraClient {
+        &self.client
+    }
+
+    /// Checks the account's network against `N`, failing with
+    /// [`NetworkError::Mismatch`] otherwise. Passes if the API doesn't report
+    /// a network.
+    pub async fn verify_network(&self) -> Result<(), NetworkError> {
+        let account = self.client.get_account().await?.data;
+        match account.network_type {
+            Some(actual) if actual != N::NETWORK_TYPE => Err(NetworkError::Mismatch {
+                expected: N::NETWORK_TYPE,
+                actual,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    pub async fn get_balance(&self) -> Result<Sats<N>, APIError> {
+        let balance = self.client.get_balance().await?.data.balance;
+        Ok(Sats::new(balance.into()))
+    }
+
+    pub async fn create_onchain_address(
+        &self,
+        request: CreateOnChainAddressRequest,
+    ) -> Result<OnChainAddress<N>, APIError> {
+        let data = self.client.create_onchain_address(request).await?.data;
+        Ok(OnChainAddress {
+            data,
+            network: PhantomData,
+        })
+    }
+
+    pub fn watch_onchain_address(&self, address: &OnChainAddress<N>) -> AddressWatcher {
+        self.client.watch_onchain_address(address)
+    }
+
+    pub async fn create_lightning_invoice(
+        &self,
+        request: CreateLightningInvoiceRequest,
+    ) -> Result<LightningInvoice<N>, APIError> {
+        let expires_at = SystemTime::now().checked_add(Duration::from_secs(request.expiry_seconds));
+        let data = self.client.create_lightning_invoice(request).await?.data;
+        Ok(LightningInvoice {
+            data,
+            expires_at,
+            network: PhantomData,
+        })
+    }
+
+    pub fn pending_invoice(&self, invoice: &LightningInvoice<N>) -> PendingInvoice {
+        let pending = self.client.pending_invoice(&invoice.id);
+        match invoice.expires_at {
+            Some(expires_at) => pending.with_expiry(expires_at),
+            None => pending,
+        }
+    }
+
+    pub async fn estimate_withdrawal(
+        &self,
+        destination: &Destination<N>,
+        amount: Sats<N>,
+    ) -> Result<EstimateWithdrawalResponse, APIError> {
+        self.client
+            .estimate_withdrawal(EstimateWithdrawalRequest {
+                amount: amount.amount() as f64,
+                currency: "sats".to_string(),
+                destination: destination.as_str().to_string(),
+            })
+            .await
+    }
+
+    pub async fn withdraw(
+        &self,
+        destination: &Destination<N>,
+        amount: Sats<N>,
+        metadata: HashMap<String, String>,
+    ) -> Result<WithdrawResponse, APIError> {
+        self.client
+            .withdraw(WithdrawRequest {
+                amount: amount.amount() as f64,
+                currency: "sats".to_string(),
+                destination: destination.as_str().to_string(),
+                metadata,
+            })
+            .await
+    }
+}
+
+impl<N> Clone for NetworkClient<N> {
+    fn clone(&self) -> Self {
+        Self {
+            client: self.client.clone(),
+            network: PhantomData,
+        }
+    }
+}
+
+impl<N: Network> fmt::Debug for NetworkClient<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct(&format!("NetworkClient<{}>", N::NETWORK_TYPE))
+            .field("client", &self.client)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{self, Reply};
+    use serde_json::json;
+
+    #[test]
+    fn test_destinations_are_checked_against_network() {
+        let address = "bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq";
+        assert_eq!(
+            Destination::<Mainnet>::parse(address).unwrap().kind(),
+            DestinationKind::OnChain
+        );
+        assert!(matches!(
+            Destination::<Signet>::parse(address),
+            Err(NetworkError::WrongNetwork {
+                expected: "signet",
+                ..
+            })
+        ));
+
+        let invoice = "lntbs210n1pjexample";
+        assert_eq!(
+            invoice.parse::<Destination<Signet>>().unwrap().kind(),
+            DestinationKind::Bolt11
+        );
+        assert!(invoice.parse::<Destination<Mainnet>>().is_err());
+        assert!("lnbcrt1pjexample".parse::<Destination<Mainnet>>().is_err());
+        assert!("lnbc1pjexample".parse::<Destination<Mainnet>>().is_ok());
+
+        assert_eq!(
+            Destination::<Mainnet>::parse("alice@bitvora.me")
+                .unwrap()
+                .kind(),
+            DestinationKind::LightningAddress
+        );
+        assert!(matches!(
+            Destination::<Mainnet>::parse("nope"),
+            Err(NetworkError::InvalidDestination(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_network_client_withdraws_in_sats_and_checks_network() {
+        let (client, requests) = test_support::client(|request| {
+            if request.path() == "/v1/account" {
+                Reply::ok(json!({
+                    "id": "c1",
+                    "name": "Acme",
+                    "network_type": "signet",
+                    "created_at": "2024-01-01T00:00:00Z",
+                }))
+            } else {
+                Reply::ok(test_support::withdrawal("w1", "pending"))
+            }
+        });
+
+        let signet = NetworkClient::<Signet>::from_client(client.clone());
+        signet.verify_network().await.unwrap();
+        let destination = "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx"
+            .parse()
+            .unwrap();
+        signet
+            .withdraw(&destination, Sats::new(21), HashMap::new())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            requests.bodies(),
+            [
+                r#"{"amount":21.0,"currency":"sats","destination":"tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx","metadata":{}}"#
+            ]
+        );
+        assert!(matches!(
+            NetworkClient::<Mainnet>::from_client(client)
+                .verify_network()
+                .await,
+            Err(NetworkError::Mismatch {
+                expected: "mainnet",
+                ..
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_unrepresentable_invoice_expiry_means_none() {
+        let (client, _) =
+            test_support::client(|_| Reply::ok(test_support::lightning_invoice("i1", false)));
+        let signet = NetworkClient::<Signet>::from_client(client);
+        let request = |expiry_seconds| CreateLightningInvoiceRequest {
+            amount: 21.0,
+            currency: "sats".to_string(),
+            description: "coffee".to_string(),
+            expiry_seconds,
+            metadata: None,
+        };
+
+        let invoice = signet
+            .create_lightning_invoice(request(u64::MAX))
+            .await
+            .unwrap();
+        assert_eq!(invoice.expires_at(), None);
+        signet.pending_invoice(&invoice);
+
+        let invoice = signet.create_lightning_invoice(request(60)).await.unwrap();
+        assert!(invoice.expires_at().unwrap() > SystemTime::now());
+    }
+}
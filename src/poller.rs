@@ -0,0 +1,750 @@
+//! Payment events by polling, for deployments that can't receive webhooks.
+//!
+//! [`Poller`] lists transactions on an interval, compares their statuses with
+//! a [`PollCursor`] of what it has already reported, fetches the deposit or
+//! withdrawal behind each change and yields it as the same [`WebhookEvent`] a
+//! webhook would carry.
+//!
+//! Each poll only lists transactions created since the oldest one that may
+//! still change, so once transactions reach a final status they drop out of
+//! the listing and out of the cursor.
+//!
+//! ```ignore
+//! let mut events = Poller::new(client)
+//!     .with_interval(Duration::from_secs(10))
+//!     .with_cursor_store(FileCursorStore::new("bitvora-cursor.json"))
+//!     .into_stream();
+//! while let Some(event) = events.next().await {
+//!     handle(event?).await;
+//! }
+//! ```
+//!
+//! Each reported event is recorded in the cursor store when the next event is
+//! requested, so an event is only marked as reported once the consumer is
+//! done with it. After a crash the last event may therefore be yielded again.
+
+use crate::client::BitvoraClient;
+use crate::errors::APIError;
+use crate::hydrate::{fetch_details, TransactionDetails};
+use crate::models::{GetDepositData, Transaction, TransactionQuery, WithdrawData};
+use crate::transactions::TransactionStream;
+use crate::webhooks::WebhookEvent;
+use futures_util::{Stream, TryStreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Statuses after which a deposit or withdrawal no longer changes.
+pub const FINAL_STATUSES: &[&str] = &[
+    "completed",
+    "confirmed",
+    "settled",
+    "succeeded",
+    "failed",
+    "expired",
+    "cancelled",
+];
+
+/// What the poller has already reported.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PollCursor {
+    /// RFC 3339 creation time of the oldest transaction that may still
+    /// change. Older transactions are final and are no longer listed.
+    pub created_after: Option<String>,
+    /// Last reported status of every transaction created since
+    /// `created_after`.
+    pub statuses: HashMap<String, String>,
+}
+
+/// Where the poller persists its cursor between restarts.
+pub trait CursorStore: Send + Sync {
+    fn load(&self) -> std::io::Result<Option<PollCursor>>;
+
+    /// Replaces the saved cursor. Called when a poll moves `created_after` or
+    /// drops entries.
+    fn save(&self, cursor: &PollCursor) -> std::io::Result<()>;
+
+    /// Records that transaction `id` was reported in `status`. `cursor`
+    /// already includes the entry; stores that can append a single entry
+    /// should do so instead of saving all of `cursor`, which is the default.
+    fn record(&self, cursor: &PollCursor, id: &str, status: &str) -> std::io::Result<()> {
+        let _ = (id, status);
+        self.save(cursor)
+    }
+}
+
+/// Keeps the cursor in memory, so it only survives recreating the poller
+/// within the same process.
+#[derive(Debug, Default)]
+pub struct MemoryCursorStore(Mutex<Option<PollCursor>>);
+
+impl MemoryCursorStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl CursorStore for MemoryCursorStore {
+    fn load(&self) -> std::io::Result<Option<PollCursor>> {
+        Ok(self.0.lock().unwrap().clone())
+    }
+
+    fn save(&self, cursor: &PollCursor) -> std::io::Result<()> {
+        *self.0.lock().unwrap() = Some(cursor.clone());
+        Ok(())
+    }
+
+    fn record(&self, _cursor: &PollCursor, id: &str, status: &str) -> std::io::Result<()> {
+        self.0
+            .lock()
+            .unwrap()
+            .get_or_insert_with(PollCursor::default)
+            .statuses
+            .insert(id.to_string(), status.to_string());
+        Ok(())
+    }
+}
+
+/// Keeps the cursor in a JSON file. Saves replace the file atomically, and
+/// each reported event is appended to it as one line, so reporting an event
+/// doesn't rewrite the whole cursor.
+#[derive(Debug)]
+pub struct FileCursorStore {
+    path: PathBuf,
+}
+
+impl FileCursorStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+/// A line appended to a [`FileCursorStore`] file for each reported event.
+#[derive(Serialize, Deserialize)]
+struct Reported {
+    id: String,
+    status: String,
+}
+
+impl CursorStore for FileCursorStore {
+    fn load(&self) -> std::io::Result<Option<PollCursor>> {
+        let contents = match std::fs::read(&self.path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err),
+        };
+
+        let mut deserializer = serde_json::Deserializer::from_slice(&contents);
+        let mut cursor = PollCursor::deserialize(&mut deserializer)?;
+        for reported in deserializer.into_iter::<Reported>() {
+            match reported {
+                Ok(Reported { id, status }) => {
+                    cursor.statuses.insert(id, status);
+                }
+                // A line cut short by a crash while it was being appended.
+                Err(err) if err.is_eof() => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+        Ok(Some(cursor))
+    }
+
+    fn save(&self, cursor: &PollCursor) -> std::io::Result<()> {
+        let mut temporary = self.path.clone().into_os_string();
+        temporary.push(".tmp");
+        let mut contents = serde_json::to_vec(cursor)?;
+        contents.push(b'\n');
+        std::fs::write(&temporary, contents)?;
+        std::fs::rename(&temporary, &self.path)
+    }
+
+    fn record(&self, cursor: &PollCursor, id: &str, status: &str) -> std::io::Result<()> {
+        if !self.path.exists() {
+            return self.save(cursor);
+        }
+        let mut line = serde_json::to_vec(&Reported {
+            id: id.to_string(),
+            status: status.to_string(),
+        })?;
+        line.push(b'\n');
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(&self.path)?
+            .write_all(&line)
+    }
+}
+
+pub struct Poller {
+    client: BitvoraClient,
+    interval: Duration,
+    store: Arc<dyn CursorStore>,
+    emit_existing: bool,
+    filter: Option<Arc<TransactionFilter>>,
+}
+
+type TransactionFilter = dyn Fn(&Transaction) -> bool + Send + Sync;
+
+impl Poller {
+    pub fn new(client: BitvoraClient) -> Self {
+        Self {
+            client,
+            interval: DEFAULT_INTERVAL,
+            store: Arc::new(MemoryCursorStore::new()),
+            emit_existing: false,
+            filter: None,
+        }
+    }
+
+    /// Time between polls once all changes have been yielded.
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    pub fn with_cursor_store(mut self, store: impl CursorStore + 'static) -> Self {
+        self.store = Arc::new(store);
+        self
+    }
+
+    /// Like [`Poller::with_cursor_store`], for a store that is shared.
+    pub fn with_shared_cursor_store(mut self, store: Arc<dyn CursorStore>) -> Self {
+        self.store = store;
+        self
+    }
+
+    /// Without a saved cursor, the first poll only records the current state.
+    /// Enable this to report every existing transaction instead.
+    pub fn emit_existing(mut self, emit: bool) -> Self {
+        self.emit_existing = emit;
+        self
+    }
+
+    /// Only watches transactions for which `filter` returns `true`.
+    pub fn with_filter(
+        mut self,
+        filter: impl Fn(&Transaction) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.filter = Some(Arc::new(filter));
+        self
+    }
+
+    /// Polls until the stream is dropped. Errors are yielded and polling
+    /// carries on; a transaction whose details couldn't be fetched is retried
+    /// on the next poll.
+    pub fn into_stream(self) -> impl Stream<Item = Result<WebhookEvent, PollError>> + Send {
+        let state = PollState {
+            poller: self,
+            cursor: None,
+            queue: VecDeque::new(),
+            uncommitted: None,
+            polled: false,
+        };
+        futures_util::stream::unfold(state, |mut state| async move {
+            let item = state.next().await;
+            Some((item, state))
+        })
+    }
+}
+
+impl std::fmt::Debug for Poller {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Poller")
+            .field("interval", &self.interval)
+            .field("emit_existing", &self.emit_existing)
+            .finish_non_exhaustive()
+    }
+}
+
+struct PollState {
+    poller: Poller,
+    cursor: Option<PollCursor>,
+    // Changed transactions still to report, as (id, status, transaction).
+    queue: VecDeque<(String, String, Transaction)>,
+    // Cursor entry of the last yielded event, saved on the next request.
+    uncommitted: Option<(String, String)>,
+    polled: bool,
+}
+
+impl PollState {
+    async fn next(&mut self) -> Result<WebhookEvent, PollError> {
+        if let Some((id, status)) = self.uncommitted.take() {
+            let cursor = self.cursor.get_or_insert_with(PollCursor::default);
+            cursor.statuses.insert(id.clone(), status.clone());
+            self.poller.store.record(cursor, &id, &status)?;
+        }
+
+        loop {
+            if let Some((id, status, transaction)) = self.queue.pop_front() {
+                let event = self.fetch(&transaction).await?;
+                self.uncommitted = Some((id, status));
+                return Ok(event);
+            }
+
+            if self.polled {
+                tokio::time::sleep(self.poller.interval).await;
+            }
+            self.polled = true;
+            self.poll().await?;
+        }
+    }
+
+    async fn poll(&mut self) -> Result<(), PollError> {
+        if self.cursor.is_none() {
+            self.cursor = self.poller.store.load()?;
+        }
+        let query = TransactionQuery {
+            created_after: self
+                .cursor
+                .as_ref()
+                .and_then(|cursor| cursor.created_after.clone()),
+            ..Default::default()
+        };
+        let mut transactions: Vec<Transaction> =
+            TransactionStream::new(self.poller.client.clone(), query)
+                .into_stream()
+                .try_collect()
+                .await?;
+        if let Some(filter) = &self.poller.filter {
+            transactions.retain(|tx| filter(tx));
+        }
+
+        let first_run = self.cursor.is_none();
+        let mut cursor = self.cursor.clone().unwrap_or_default();
+        if first_run && !self.poller.emit_existing {
+            // First run: just remember the current state.
+            cursor.statuses = transactions
+                .iter()
+                .map(|tx| (tx.id.clone(), tx.status.clone()))
+                .collect();
+        }
+
+        let queue: VecDeque<_> = transactions
+            .iter()
+            .filter(|tx| cursor.statuses.get(&tx.id) != Some(&tx.status))
+            .map(|tx| (tx.id.clone(), tx.status.clone(), tx.clone()))
+            .collect();
+
+        // Keep listing from the oldest transaction that may still change or
+        // is about to be reported. Once everything is final, only the newest
+        // transaction is listed again. The API's RFC 3339 UTC timestamps
+        // order correctly as strings.
+        let queued: HashSet<&str> = queue.iter().map(|(id, ..)| id.as_str()).collect();
+        let open = transactions
+            .iter()
+            .filter(|tx| !is_final(&tx.status) || queued.contains(tx.id.as_str()))
+            .map(|tx| tx.created_at.as_str())
+            .min();
+        let newest = transactions.iter().map(|tx| tx.created_at.as_str()).max();
+        if let Some(created_after) = open.or(newest) {
+            cursor.created_after = Some(created_after.to_string());
+        }
+
+        // Transactions before it are no longer listed, so forget them.
+        let listed: HashMap<&str, &str> = transactions
+            .iter()
+            .map(|tx| (tx.id.as_str(), tx.created_at.as_str()))
+            .collect();
+        let created_after = cursor.created_after.clone().unwrap_or_default();
+        cursor.statuses.retain(|id, _| {
+            listed
+                .get(id.as_str())
+                .is_some_and(|created_at| *created_at >= created_after.as_str())
+        });
+
+        // Nothing changes until the cursor is saved, so a failed save leaves
+        // the previous cursor to retry from.
+        if self.cursor.as_ref() != Some(&cursor) {
+            self.poller.store.save(&cursor)?;
+        }
+        self.cursor = Some(cursor);
+        self.queue = queue;
+        Ok(())
+    }
+
+    async fn fetch(&self, transaction: &Transaction) -> Result<WebhookEvent, APIError> {
+        Ok(
+            match fetch_details(&self.poller.client, transaction).await? {
+                TransactionDetails::Deposit(deposit) => deposit_event(*deposit),
+                TransactionDetails::Withdrawal(withdrawal) => withdrawal_event(*withdrawal),
+                // No record found: report the ledger row itself.
+                TransactionDetails::None => status_changed(
+                    &format!("{}.status_changed", transaction.r#type),
+                    transaction,
+                ),
+            },
+        )
+    }
+}
+
+fn is_final(status: &str) -> bool {
+    FINAL_STATUSES.contains(&status)
+}
+
+#[derive(Debug)]
+pub enum PollError {
+    Api(APIError),
+    /// Loading or saving the cursor failed.
+    Cursor(std::io::Error),
+}
+
+impl std::fmt::Display for PollError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PollError::Api(err) => write!(f, "{}", err),
+            PollError::Cursor(err) => write!(f, "Poll cursor error: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for PollError {}
+
+impl From<APIError> for PollError {
+    fn from(err: APIError) -> Self {
+        PollError::Api(err)
+    }
+}
+
+impl From<std::io::Error> for PollError {
+    fn from(err: std::io::Error) -> Self {
+        PollError::Cursor(err)
+    }
+}
+
+/// The webhook event for a deposit in its current status. Statuses without a
+/// webhook of their own become `deposit.status_changed`.
+pub fn deposit_event(deposit: GetDepositData) -> WebhookEvent {
+    let onchain = deposit.rail_type == "onchain";
+    match deposit.status.as_str() {
+        "pending" | "unconfirmed" if onchain => WebhookEvent::DepositOnChainPending(deposit),
+        "completed" | "confirmed" | "settled" if onchain => {
+            WebhookEvent::DepositOnChainCompleted(deposit)
+        }
+        "completed" | "settled" => WebhookEvent::DepositLightningCompleted(deposit),
+        "failed" if !onchain => WebhookEvent::DepositLightningFailed(deposit),
+        _ => status_changed("deposit.status_changed", &deposit),
+    }
+}
+
+/// The webhook event for a withdrawal in its current status. Statuses without
+/// a webhook of their own become `withdrawal.status_changed`.
+pub fn withdrawal_event(withdrawal: WithdrawData) -> WebhookEvent {
+    match withdrawal.status.as_str() {
+        "pending" => WebhookEvent::WithdrawalPending(withdrawal),
+        "completed" | "settled" | "succeeded" => WebhookEvent::WithdrawalCompleted(withdrawal),
+        "failed" => WebhookEvent::WithdrawalFailed(withdrawal),
+        _ => status_changed("withdrawal.status_changed", &withdrawal),
+    }
+}
+
+fn status_changed(event: &str, data: &impl Serialize) -> WebhookEvent {
+    WebhookEvent::Unknown {
+        event: event.to_string(),
+        data: serde_json::to_value(data).unwrap_or_default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{self, with, Reply, Requests};
+    use futures_util::StreamExt;
+    use serde_json::{json, Value};
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    type Responses = Arc<Mutex<HashMap<String, Value>>>;
+
+    /// Serves `responses` by path, listing only transactions created at or
+    /// after the `created_after` parameter.
+    fn client(responses: Responses) -> (BitvoraClient, Requests) {
+        test_support::client(move |request| {
+            let responses = responses.lock().unwrap();
+            if request.path() != "/v1/transactions" {
+                return Reply::ok(responses[&request.target()].clone());
+            }
+            let data = responses[request.path()].clone();
+            match (data, request.param("created_after")) {
+                (Value::Array(list), Some(after)) => Reply::ok(
+                    list.into_iter()
+                        .filter(|tx| tx["created_at"].as_str().unwrap() >= after.as_str())
+                        .collect(),
+                ),
+                (data, _) => Reply::ok(data),
+            }
+        })
+    }
+
+    fn created_on(day: u8) -> String {
+        format!("2024-01-{day:02}T00:00:00Z")
+    }
+
+    /// Serves transactions as `(id, kind, rail_type, status, day created)`.
+    fn set(responses: &Responses, transactions: &[(&str, &str, &str, &str, u8)]) {
+        let mut responses = responses.lock().unwrap();
+        let mut list = Vec::new();
+        for (id, kind, rail_type, status, day) in transactions {
+            let fields = json!({"rail_type": rail_type, "ledger_tx_id": id});
+            list.push(with(
+                test_support::transaction(id, kind, status),
+                json!({"rail_type": rail_type, "created_at": created_on(*day)}),
+            ));
+            // Deposits and withdrawals have ids of their own, and are listed
+            // by the ledger id of their transaction.
+            let (target, data) = if *kind == "withdrawal" {
+                (
+                    format!("/v1/transactions/withdrawals?ledger_tx_id={id}"),
+                    test_support::withdrawal(&format!("wd_{id}"), status),
+                )
+            } else {
+                (
+                    format!("/v1/transactions/deposits?ledger_tx_id={id}"),
+                    test_support::deposit(&format!("dep_{id}"), status),
+                )
+            };
+            responses.insert(target, json!([with(data, fields)]));
+        }
+        responses.insert("/v1/transactions".to_string(), json!(list));
+    }
+
+    fn poller(responses: &Responses, store: &Arc<MemoryCursorStore>) -> Poller {
+        Poller::new(client(responses.clone()).0)
+            .with_interval(Duration::from_millis(1))
+            .with_shared_cursor_store(store.clone())
+    }
+
+    #[tokio::test]
+    async fn test_status_changes_become_events() {
+        let responses = Responses::default();
+        let store = Arc::new(MemoryCursorStore::new());
+        set(
+            &responses,
+            &[("w1", "withdrawal", "lightning", "pending", 1)],
+        );
+
+        let mut events = Box::pin(poller(&responses, &store).emit_existing(true).into_stream());
+        let event = events.next().await.unwrap().unwrap();
+        assert!(matches!(event, WebhookEvent::WithdrawalPending(_)));
+
+        set(
+            &responses,
+            &[
+                ("w1", "withdrawal", "lightning", "completed", 1),
+                ("d1", "deposit", "lightning", "completed", 2),
+            ],
+        );
+        let event = events.next().await.unwrap().unwrap();
+        assert!(matches!(event, WebhookEvent::WithdrawalCompleted(_)));
+        let event = events.next().await.unwrap().unwrap();
+        assert!(matches!(event, WebhookEvent::DepositLightningCompleted(_)));
+        drop(events);
+
+        // The cursor survives a restart; only the unacknowledged event is
+        // reported again.
+        let mut events = Box::pin(poller(&responses, &store).into_stream());
+        let event = events.next().await.unwrap().unwrap();
+        assert_eq!(event.object_id(), Some("dep_d1"));
+        // The completed withdrawal is older than anything still open, so it
+        // is no longer listed or kept.
+        let cursor = store.load().unwrap().unwrap();
+        assert_eq!(cursor.created_after, Some(created_on(2)));
+        assert!(!cursor.statuses.contains_key("w1"));
+    }
+
+    #[tokio::test]
+    async fn test_first_run_skips_history_by_default() {
+        let responses = Responses::default();
+        let store = Arc::new(MemoryCursorStore::new());
+        set(&responses, &[("d1", "deposit", "onchain", "completed", 1)]);
+        let mut events = Box::pin(poller(&responses, &store).into_stream());
+
+        let next = tokio::time::timeout(Duration::from_millis(50), events.next()).await;
+        assert!(next.is_err());
+
+        set(
+            &responses,
+            &[
+                ("d1", "deposit", "onchain", "completed", 1),
+                ("d2", "deposit", "onchain", "pending", 2),
+            ],
+        );
+        let event = events.next().await.unwrap().unwrap();
+        assert!(matches!(event, WebhookEvent::DepositOnChainPending(_)));
+        assert_eq!(event.object_id(), Some("dep_d2"));
+    }
+
+    #[tokio::test]
+    async fn test_final_transactions_drop_out_of_the_cursor() {
+        let responses = Responses::default();
+        let store = Arc::new(MemoryCursorStore::new());
+        set(
+            &responses,
+            &[
+                ("d1", "deposit", "lightning", "completed", 1),
+                ("w1", "withdrawal", "lightning", "pending", 2),
+                ("d2", "deposit", "lightning", "failed", 3),
+            ],
+        );
+        let (client, requests) = client(responses.clone());
+        let mut events = Box::pin(
+            Poller::new(client)
+                .with_interval(Duration::from_millis(1))
+                .with_shared_cursor_store(store.clone())
+                .into_stream(),
+        );
+
+        let next = tokio::time::timeout(Duration::from_millis(20), events.next()).await;
+        assert!(next.is_err());
+        // Listing starts at the pending withdrawal; d1 is forgotten.
+        let cursor = store.load().unwrap().unwrap();
+        assert_eq!(cursor.created_after, Some(created_on(2)));
+        assert_eq!(
+            cursor.statuses,
+            HashMap::from([
+                ("w1".to_string(), "pending".to_string()),
+                ("d2".to_string(), "failed".to_string()),
+            ])
+        );
+        assert_eq!(requests.all()[0].param("created_after"), None);
+        assert_eq!(
+            requests.all().last().unwrap().param("created_after"),
+            Some(created_on(2))
+        );
+
+        set(
+            &responses,
+            &[
+                ("d1", "deposit", "lightning", "completed", 1),
+                ("w1", "withdrawal", "lightning", "completed", 2),
+                ("d2", "deposit", "lightning", "failed", 3),
+            ],
+        );
+        let event = events.next().await.unwrap().unwrap();
+        assert!(matches!(event, WebhookEvent::WithdrawalCompleted(_)));
+        let next = tokio::time::timeout(Duration::from_millis(20), events.next()).await;
+        assert!(next.is_err());
+
+        // Everything is final: only the newest transaction is still listed.
+        let cursor = store.load().unwrap().unwrap();
+        assert_eq!(cursor.created_after, Some(created_on(3)));
+        assert_eq!(
+            cursor.statuses,
+            HashMap::from([("d2".to_string(), "failed".to_string())])
+        );
+    }
+
+    /// A [`MemoryCursorStore`] whose next save fails once `fail_next` is set.
+    #[derive(Default)]
+    struct FailingStore {
+        inner: MemoryCursorStore,
+        fail_next: AtomicBool,
+    }
+
+    impl CursorStore for FailingStore {
+        fn load(&self) -> std::io::Result<Option<PollCursor>> {
+            self.inner.load()
+        }
+
+        fn save(&self, cursor: &PollCursor) -> std::io::Result<()> {
+            if self.fail_next.swap(false, Ordering::SeqCst) {
+                return Err(std::io::Error::other("disk full"));
+            }
+            self.inner.save(cursor)
+        }
+
+        fn record(&self, cursor: &PollCursor, id: &str, status: &str) -> std::io::Result<()> {
+            self.inner.record(cursor, id, status)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failed_saves_keep_the_previous_cursor() {
+        let responses = Responses::default();
+        let store = Arc::new(FailingStore::default());
+        set(
+            &responses,
+            &[
+                ("w1", "withdrawal", "lightning", "pending", 1),
+                ("w2", "withdrawal", "lightning", "pending", 2),
+            ],
+        );
+        let mut events = Box::pin(
+            Poller::new(client(responses.clone()).0)
+                .with_interval(Duration::from_millis(1))
+                .with_shared_cursor_store(store.clone())
+                .into_stream(),
+        );
+        let next = tokio::time::timeout(Duration::from_millis(20), events.next()).await;
+        assert!(next.is_err());
+
+        set(
+            &responses,
+            &[
+                ("w1", "withdrawal", "lightning", "completed", 1),
+                ("w2", "withdrawal", "lightning", "pending", 2),
+            ],
+        );
+        let event = events.next().await.unwrap().unwrap();
+        assert_eq!(event.object_id(), Some("wd_w1"));
+
+        // The poll that reports w2 also moves the cursor past w1, and saving
+        // that fails.
+        store.fail_next.store(true, Ordering::SeqCst);
+        set(
+            &responses,
+            &[
+                ("w1", "withdrawal", "lightning", "completed", 1),
+                ("w2", "withdrawal", "lightning", "completed", 2),
+            ],
+        );
+        assert!(matches!(
+            events.next().await.unwrap(),
+            Err(PollError::Cursor(_))
+        ));
+        let event = events.next().await.unwrap().unwrap();
+        assert_eq!(event.object_id(), Some("wd_w2"));
+
+        // w1 isn't reported again.
+        let next = tokio::time::timeout(Duration::from_millis(20), events.next()).await;
+        assert!(next.is_err());
+        let cursor = store.load().unwrap().unwrap();
+        assert_eq!(cursor.created_after, Some(created_on(2)));
+    }
+
+    #[test]
+    fn test_file_cursor_store_appends_reported_events() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cursor.json");
+        let store = FileCursorStore::new(&path);
+        assert_eq!(store.load().unwrap(), None);
+
+        let mut cursor = PollCursor {
+            created_after: Some(created_on(1)),
+            statuses: HashMap::from([("w1".to_string(), "pending".to_string())]),
+        };
+        store.save(&cursor).unwrap();
+        assert_eq!(store.load().unwrap(), Some(cursor.clone()));
+
+        cursor
+            .statuses
+            .insert("w1".to_string(), "completed".to_string());
+        store.record(&cursor, "w1", "completed").unwrap();
+        cursor
+            .statuses
+            .insert("d1".to_string(), "completed".to_string());
+        store.record(&cursor, "d1", "completed").unwrap();
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(contents.lines().count(), 3);
+        assert_eq!(store.load().unwrap(), Some(cursor.clone()));
+
+        // A line cut short by a crash is ignored; saving compacts the file.
+        std::fs::write(&path, format!("{contents}{{\"id\":\"d2")).unwrap();
+        assert_eq!(store.load().unwrap(), Some(cursor.clone()));
+        store.save(&cursor).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap().lines().count(), 1);
+    }
+}
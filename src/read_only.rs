@@ -0,0 +1,336 @@
+//! A client that can't move money.
+//!
+//! [`ReadOnlyClient`] only has the lookup and listing methods of
+//! [`BitvoraClient`], and only acts as a `tower::Service` for operations
+//! marked [`ReadOperation`], so code handed one can't call `withdraw` or
+//! create payment requests; it doesn't compile.
+//!
+//! ```ignore
+//! let reporting = BitvoraClient::new(url, key).into_read_only();
+//! reporting.verify_scopes().await?;
+//! let balance = reporting.get_balance().await?;
+//! ```
+//!
+//! [`PaymentsClient`] is the full client, for the services that do move
+//! money.
+
+use crate::errors::{APIError, ScopeError};
+use crate::hydrate::Hydrator;
+use crate::models::*;
+use crate::poller::Poller;
+use crate::service::{ReadOperation, ResponseFuture};
+use crate::BitvoraClient;
+use futures_util::Stream;
+use reqwest::StatusCode;
+use std::task::{Context, Poll};
+use tower::Service;
+
+/// The full client, including withdrawals.
+pub type PaymentsClient = BitvoraClient;
+
+/// Scopes a read-only client's key should not have. Managing webhooks is
+/// among them, as webhook responses can carry signing secrets.
+const PAYMENT_SCOPES: &[ApiKeyScope] = &[
+    ApiKeyScope::Withdraw,
+    ApiKeyScope::ManageApiKeys,
+    ApiKeyScope::ManageWebhooks,
+];
+
+/// Lookups and listings only. Created with [`BitvoraClient::into_read_only`];
+/// there is no way back to the full client.
+///
+/// ```compile_fail
+/// # async fn pay(client: bitvora::ReadOnlyClient, request: bitvora::WithdrawRequest) {
+/// client.withdraw(request).await;
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ReadOnlyClient {
+    client: BitvoraClient,
+}
+
+impl ReadOnlyClient {
+    pub fn new(client: BitvoraClient) -> Self {
+        Self { client }
+    }
+
+    /// Checks that the key can't withdraw, mint keys or manage webhooks,
+    /// failing with [`ScopeError::Excess`] otherwise, so a service isn't
+    /// handed a broader key than its client type suggests. Passes when the
+    /// API doesn't support key introspection.
+    pub async fn verify_scopes(&self) -> Result<(), ScopeError> {
+        let Some(scopes) = current_scopes(&self.client).await? else {
+            return Ok(());
+        };
+        let excess: Vec<_> = scopes
+            .into_iter()
+            .filter(|scope| PAYMENT_SCOPES.contains(scope))
+            .collect();
+        if excess.is_empty() {
+            Ok(())
+        } else {
+            Err(ScopeError::Excess(excess))
+        }
+    }
+
+    pub async fn get_balance(&self) -> Result<GetBalanceResponse, APIError> {
+        self.client.get_balance().await
+    }
+
+    pub async fn get_transactions(&self) -> Result<GetTransactionsResponse, APIError> {
+        self.client.get_transactions().await
+    }
+
+    pub async fn list_transactions(
+        &self,
+        query: TransactionQuery,
+    ) -> Result<ListTransactionsResponse, APIError> {
+        self.client.list_transactions(query).await
+    }
+
+    pub fn transactions_stream(
+        &self,
+        filter: TransactionQuery,
+    ) -> impl Stream<Item = Result<Transaction, APIError>> + Send {
+        self.client.transactions_stream(filter)
+    }
+
+    pub fn hydrator(&self) -> Hydrator {
+        self.client.hydrator()
+    }
+
+    pub fn poller(&self) -> Poller {
+        Poller::new(self.client.clone())
+    }
+
+    pub async fn get_withdrawal(&self, id: &str) -> Result<WithdrawResponse, APIError> {
+        self.client.get_withdrawal(id).await
+    }
+
+    pub async fn get_deposit(&self, id: &str) -> Result<GetDepositResponse, APIError> {
+        self.client.get_deposit(id).await
+    }
+
+    pub async fn list_withdrawals(
+        &self,
+        query: PaymentQuery,
+    ) -> Result<ListWithdrawalsResponse, APIError> {
+        self.client.list_withdrawals(query).await
+    }
+
+    pub async fn list_deposits(
+        &self,
+        query: PaymentQuery,
+    ) -> Result<ListDepositsResponse, APIError> {
+        self.client.list_deposits(query).await
+    }
+
+    pub async fn estimate_withdrawal(
+        &self,
+        request: EstimateWithdrawalRequest,
+    ) -> Result<EstimateWithdrawalResponse, APIError> {
+        self.client.estimate_withdrawal(request).await
+    }
+
+    pub async fn get_lightning_invoice(
+        &self,
+        id: &str,
+    ) -> Result<CreateLightningInvoiceResponse, APIError> {
+        self.client.get_lightning_invoice(id).await
+    }
+
+    pub async fn get_lightning_invoice_by_hash(
+        &self,
+        r_hash: &str,
+    ) -> Result<CreateLightningInvoiceResponse, APIError> {
+        self.client.get_lightning_invoice_by_hash(r_hash).await
+    }
+
+    pub async fn list_lightning_invoices(
+        &self,
+        query: LightningInvoiceQuery,
+    ) -> Result<ListLightningInvoicesResponse, APIError> {
+        self.client.list_lightning_invoices(query).await
+    }
+
+    pub async fn list_lightning_addresses(
+        &self,
+    ) -> Result<ListLightningAddressesResponse, APIError> {
+        self.client.list_lightning_addresses().await
+    }
+
+    pub async fn get_lightning_address(
+        &self,
+        id: &str,
+    ) -> Result<CreateLightningAddressResponse, APIError> {
+        self.client.get_lightning_address(id).await
+    }
+
+    pub async fn get_lightning_address_by_handle(
+        &self,
+        handle: &str,
+    ) -> Result<CreateLightningAddressResponse, APIError> {
+        self.client.get_lightning_address_by_handle(handle).await
+    }
+
+    pub async fn list_lightning_address_deposits(
+        &self,
+        id: &str,
+    ) -> Result<ListDepositsResponse, APIError> {
+        self.client.list_lightning_address_deposits(id).await
+    }
+
+    pub async fn list_onchain_addresses(&self) -> Result<ListOnChainAddressesResponse, APIError> {
+        self.client.list_onchain_addresses().await
+    }
+
+    pub async fn get_onchain_address(
+        &self,
+        id: &str,
+    ) -> Result<CreateOnChainAddressResponse, APIError> {
+        self.client.get_onchain_address(id).await
+    }
+
+    pub async fn get_onchain_address_by_address(
+        &self,
+        address: &str,
+    ) -> Result<CreateOnChainAddressResponse, APIError> {
+        self.client.get_onchain_address_by_address(address).await
+    }
+
+    pub async fn list_onchain_address_deposits(
+        &self,
+        id: &str,
+    ) -> Result<ListDepositsResponse, APIError> {
+        self.client.list_onchain_address_deposits(id).await
+    }
+
+    pub async fn get_account(&self) -> Result<AccountResponse, APIError> {
+        self.client.get_account().await
+    }
+
+    pub async fn get_current_api_key(&self) -> Result<ApiKeyResponse, APIError> {
+        self.client.get_current_api_key().await
+    }
+}
+
+impl<O: ReadOperation> Service<O> for ReadOnlyClient {
+    type Response = O::Response;
+    type Error = APIError;
+    type Future = ResponseFuture<O::Response>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Service::<O>::poll_ready(&mut self.client, cx)
+    }
+
+    fn call(&mut self, operation: O) -> Self::Future {
+        self.client.call(operation)
+    }
+}
+
+impl BitvoraClient {
+    /// Gives up the payment methods, for services that must never move
+    /// money.
+    pub fn into_read_only(self) -> ReadOnlyClient {
+        ReadOnlyClient::new(self)
+    }
+
+    /// Checks that the key has every scope in `scopes`, failing with
+    /// [`ScopeError::Missing`] otherwise. Passes when the API doesn't support
+    /// key introspection.
+    pub async fn require_scopes(&self, scopes: &[ApiKeyScope]) -> Result<(), ScopeError> {
+        let Some(granted) = current_scopes(self).await? else {
+            return Ok(());
+        };
+        match scopes.iter().find(|scope| !granted.contains(scope)) {
+            Some(missing) => Err(ScopeError::Missing(missing.clone())),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Scopes of the client's key, or `None` if the API can't tell.
+async fn current_scopes(client: &BitvoraClient) -> Result<Option<Vec<ApiKeyScope>>, APIError> {
+    match client.get_current_api_key().await {
+        Ok(response) => Ok(Some(response.data.scopes)),
+        Err(APIError::BadRequest(StatusCode::NOT_FOUND | StatusCode::NOT_IMPLEMENTED, _)) => {
+            Ok(None)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service::{GetBalance, GetCurrentApiKey};
+    use crate::test_support::{self, Reply};
+    use serde_json::json;
+    use tower::ServiceExt;
+
+    /// A client whose key has `scopes`, or whose API lacks key introspection
+    /// when `None`.
+    fn client(scopes: Option<&'static [&'static str]>) -> BitvoraClient {
+        let (client, _) = test_support::client(move |request| match (request.path(), scopes) {
+            ("/v1/api-keys/current", Some(scopes)) => Reply::ok(json!({
+                "id": "k1",
+                "name": "svc",
+                "scopes": scopes,
+                "created_at": "2024-01-01T00:00:00Z",
+                "last_used_at": null,
+                "revoked_at": null,
+            })),
+            ("/v1/api-keys/current", None) => Reply::status(404, "not found"),
+            _ => Reply::ok(json!({"balance": 42})),
+        });
+        client
+    }
+
+    #[tokio::test]
+    async fn test_read_only_client_serves_read_operations() {
+        let read_only = client(None).into_read_only();
+
+        assert_eq!(read_only.get_balance().await.unwrap().data.balance, 42);
+        let response = read_only.clone().oneshot(GetBalance).await.unwrap();
+        assert_eq!(response.data.balance, 42);
+        // Without introspection there is nothing to check.
+        read_only.verify_scopes().await.unwrap();
+        read_only.oneshot(GetCurrentApiKey).await.unwrap_err();
+    }
+
+    #[tokio::test]
+    async fn test_scope_checks() {
+        let reader = client(Some(&["read"]));
+        let payer = client(Some(&["read", "withdraw"]));
+
+        reader
+            .clone()
+            .into_read_only()
+            .verify_scopes()
+            .await
+            .unwrap();
+        match payer.clone().into_read_only().verify_scopes().await {
+            Err(ScopeError::Excess(scopes)) => assert_eq!(scopes, vec![ApiKeyScope::Withdraw]),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        match client(Some(&["read", "manage_webhooks"]))
+            .into_read_only()
+            .verify_scopes()
+            .await
+        {
+            Err(ScopeError::Excess(scopes)) => {
+                assert_eq!(scopes, vec![ApiKeyScope::ManageWebhooks])
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        payer
+            .require_scopes(&[ApiKeyScope::Withdraw])
+            .await
+            .unwrap();
+        assert!(matches!(
+            reader.require_scopes(&[ApiKeyScope::Withdraw]).await,
+            Err(ScopeError::Missing(ApiKeyScope::Withdraw))
+        ));
+    }
+}
@@ -0,0 +1,252 @@
+//! API key handling.
+//!
+//! [`ApiKey`] keeps the key out of `Debug`/`Display` output and wipes it from
+//! memory on drop. The client asks its [`SecretProvider`] for the key before
+//! every request, so providers such as [`FileSecretProvider`] can rotate the
+//! key without the client being recreated.
+
+use futures_util::future::BoxFuture;
+use serde::{Deserialize, Deserializer};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use zeroize::Zeroizing;
+
+#[derive(Clone)]
+pub struct ApiKey(Zeroizing<String>);
+
+impl ApiKey {
+    pub fn new(key: impl Into<String>) -> Self {
+        ApiKey(Zeroizing::new(key.into()))
+    }
+
+    /// Reads the key from the environment variable `name`.
+    pub fn from_env(name: &str) -> Result<Self, SecretError> {
+        let key = std::env::var(name).map_err(|_| SecretError::MissingEnv(name.to_string()))?;
+        Self::parse(key)
+    }
+
+    /// Reads the key from a file, ignoring surrounding whitespace.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, SecretError> {
+        let contents = Zeroizing::new(std::fs::read_to_string(path)?);
+        Self::parse(contents.as_str())
+    }
+
+    /// The raw key, for placing in the `Authorization` header.
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+
+    fn parse(key: impl AsRef<str>) -> Result<Self, SecretError> {
+        let key = key.as_ref().trim();
+        if key.is_empty() {
+            return Err(SecretError::Empty);
+        }
+        Ok(Self::new(key))
+    }
+}
+
+impl<'de> Deserialize<'de> for ApiKey {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Self::new)
+    }
+}
+
+impl fmt::Debug for ApiKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ApiKey([REDACTED])")
+    }
+}
+
+impl fmt::Display for ApiKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("[REDACTED]")
+    }
+}
+
+#[derive(Debug)]
+pub enum SecretError {
+    MissingEnv(String),
+    Io(std::io::Error),
+    Empty,
+    Other(String),
+}
+
+impl fmt::Display for SecretError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SecretError::MissingEnv(name) => write!(f, "Environment variable {} is not set", name),
+            SecretError::Io(err) => write!(f, "Failed to read API key: {}", err),
+            SecretError::Empty => write!(f, "API key is empty"),
+            SecretError::Other(message) => write!(f, "Secret provider error: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for SecretError {}
+
+impl From<std::io::Error> for SecretError {
+    fn from(err: std::io::Error) -> Self {
+        SecretError::Io(err)
+    }
+}
+
+/// Source of the API key, consulted before every request.
+///
+/// Implement it to fetch keys from a vault or secret manager; cache the key
+/// inside the provider, as it is called on every request. The returned
+/// future runs on the caller's runtime, so do I/O asynchronously.
+pub trait SecretProvider: Send + Sync {
+    fn api_key(&self) -> BoxFuture<'_, Result<Arc<ApiKey>, SecretError>>;
+}
+
+/// A fixed key. Each call hands out a fresh copy, which costs far less than
+/// the request it authenticates.
+impl SecretProvider for ApiKey {
+    fn api_key(&self) -> BoxFuture<'_, Result<Arc<ApiKey>, SecretError>> {
+        Box::pin(std::future::ready(Ok(Arc::new(self.clone()))))
+    }
+}
+
+/// A fixed key, shared rather than copied.
+impl SecretProvider for Arc<ApiKey> {
+    fn api_key(&self) -> BoxFuture<'_, Result<Arc<ApiKey>, SecretError>> {
+        Box::pin(std::future::ready(Ok(self.clone())))
+    }
+}
+
+/// Reads the key from a local file on every call, without blocking the
+/// runtime, and keeps the parsed key until the file's contents change, so the
+/// key can be rotated by rewriting the file.
+pub struct FileSecretProvider {
+    path: PathBuf,
+    cached: Mutex<Option<Arc<ApiKey>>>,
+}
+
+impl FileSecretProvider {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            cached: Mutex::new(None),
+        }
+    }
+}
+
+impl SecretProvider for FileSecretProvider {
+    fn api_key(&self) -> BoxFuture<'_, Result<Arc<ApiKey>, SecretError>> {
+        Box::pin(async move {
+            // Timestamps and sizes can't tell two keys of the same length
+            // written within the filesystem's timestamp resolution apart, so
+            // compare the contents themselves.
+            let contents = Zeroizing::new(tokio::fs::read_to_string(&self.path).await?);
+
+            let mut cached = self.cached.lock().unwrap();
+            if let Some(key) = cached.as_ref() {
+                if key.expose() == contents.trim() {
+                    return Ok(key.clone());
+                }
+            }
+
+            let key = Arc::new(ApiKey::parse(contents.as_str())?);
+            *cached = Some(key.clone());
+            Ok(key)
+        })
+    }
+}
+
+impl fmt::Debug for FileSecretProvider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FileSecretProvider")
+            .field("path", &self.path)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_api_key_is_never_formatted() {
+        let key = ApiKey::new("sk_live_123");
+        assert_eq!(format!("{:?}", key), "ApiKey([REDACTED])");
+        assert_eq!(format!("{}", key), "[REDACTED]");
+        assert_eq!(key.expose(), "sk_live_123");
+    }
+
+    #[tokio::test]
+    async fn test_file_provider_reloads_rotated_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("api_key");
+        std::fs::write(&path, "first-key\n").unwrap();
+
+        let provider = FileSecretProvider::new(&path);
+        assert_eq!(provider.api_key().await.unwrap().expose(), "first-key");
+
+        std::fs::write(&path, "rotated-second-key\n").unwrap();
+        assert_eq!(
+            provider.api_key().await.unwrap().expose(),
+            "rotated-second-key"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_file_provider_reloads_key_of_the_same_length() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("api_key");
+        std::fs::write(&path, "key-aaaa").unwrap();
+        let modified = std::fs::metadata(&path).unwrap().modified().unwrap();
+
+        let provider = FileSecretProvider::new(&path);
+        let first = provider.api_key().await.unwrap();
+        assert_eq!(first.expose(), "key-aaaa");
+        assert!(Arc::ptr_eq(&first, &provider.api_key().await.unwrap()));
+
+        // Same length and, as far as the provider can tell, the same mtime.
+        std::fs::write(&path, "key-bbbb").unwrap();
+        std::fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+        assert_eq!(provider.api_key().await.unwrap().expose(), "key-bbbb");
+    }
+
+    #[test]
+    fn test_empty_key_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("api_key");
+        std::fs::write(&path, "  \n").unwrap();
+
+        assert!(matches!(ApiKey::from_file(&path), Err(SecretError::Empty)));
+    }
+
+    #[tokio::test]
+    async fn test_client_sends_current_key_and_hides_it_from_debug() {
+        use crate::test_support::{self, Reply};
+        use serde_json::json;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("api_key");
+        std::fs::write(&path, "first-key").unwrap();
+
+        let (client, requests) = test_support::client(|_| Reply::ok(json!({"balance": 1})));
+        let client = client.with_secret_provider(FileSecretProvider::new(&path));
+
+        client.get_balance().await.unwrap();
+        std::fs::write(&path, "rotated-second-key").unwrap();
+        client.get_balance().await.unwrap();
+
+        let authorizations: Vec<_> = requests
+            .all()
+            .into_iter()
+            .filter_map(|request| request.authorization)
+            .collect();
+        assert_eq!(
+            authorizations,
+            ["Bearer first-key", "Bearer rotated-second-key"]
+        );
+        assert!(!format!("{:?}", client).contains("key"));
+    }
+}
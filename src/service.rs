@@ -0,0 +1,1232 @@
+use crate::client::BitvoraClient;
+use crate::errors::APIError;
+use crate::models::{
+    AccountResponse, ApiKeyResponse, CreateApiKeyRequest, CreateLightningAddressRequest,
+    CreateLightningAddressResponse, CreateLightningInvoiceRequest, CreateLightningInvoiceResponse,
+    CreateOnChainAddressRequest, CreateOnChainAddressResponse, CreateWebhookRequest,
+    DeleteLightningAddressResponse, DeleteWebhookResponse, EstimateWithdrawalRequest,
+    EstimateWithdrawalResponse, GetBalanceResponse, GetDepositResponse, GetTransactionsResponse,
+    LightningInvoiceQuery, ListApiKeysResponse, ListDepositsResponse,
+    ListLightningAddressesResponse, ListLightningInvoicesResponse, ListOnChainAddressesResponse,
+    ListTransactionsResponse, ListWebhooksResponse, ListWithdrawalsResponse, PaymentQuery,
+    RevokeApiKeyResponse, SendTestWebhookRequest, SendTestWebhookResponse, TransactionQuery,
+    UpdateLightningAddressRequest, UpdateWebhookRequest, WebhookResponse, WithdrawRequest,
+    WithdrawResponse,
+};
+use reqwest::{Method, Url};
+use serde::de::DeserializeOwned;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tower::Service;
+
+/// Percent-encodes a caller-supplied id for use as one path segment, so an
+/// id containing `/`, `?` or `#` can't reach a different endpoint.
+///
+/// Empty and dot-only ids can't be expressed as a segment; they encode to an
+/// empty string, and requests with an empty segment are refused by the
+/// client.
+pub fn segment(id: &str) -> String {
+    let mut url = Url::parse("http://localhost").expect("valid URL");
+    url.path_segments_mut().expect("URL with a path").push(id);
+    url.path()[1..].to_string()
+}
+
+/// A single Bitvora API call.
+///
+/// Every endpoint of [`BitvoraClient`] is described by one of the types in this
+/// module, which lets the client itself act as a `tower::Service` for each of
+/// them.
+pub trait Operation: Send + 'static {
+    type Response: DeserializeOwned + Send + 'static;
+
+    /// Stable name of the endpoint, e.g. `"withdraw"`.
+    const NAME: &'static str;
+
+    fn method(&self) -> Method;
+
+    /// Path relative to the client's base URL, starting with `/`.
+    fn path(&self) -> String;
+
+    /// Query string parameters, if the endpoint takes any.
+    fn query(&self) -> Vec<(String, String)> {
+        Vec::new()
+    }
+
+    /// JSON request body, if the endpoint takes one.
+    fn body(&self) -> Result<Option<Vec<u8>>, serde_json::Error> {
+        Ok(None)
+    }
+
+    /// Records request identifiers on the call's span. Sensitive values must
+    /// go through [`crate::trace::redact`].
+    #[cfg(feature = "tracing")]
+    fn record(&self, _span: &tracing::Span) {}
+
+    /// Records identifiers assigned by the API on the call's span.
+    #[cfg(feature = "tracing")]
+    fn record_response(_response: &Self::Response, _span: &tracing::Span) {}
+
+    /// Emits business metrics, such as sats withdrawn, for a successful call.
+    #[cfg(feature = "metrics")]
+    fn record_metrics(_response: &Self::Response) {}
+}
+
+pub type ResponseFuture<T> = Pin<Box<dyn Future<Output = Result<T, APIError>> + Send>>;
+
+/// Marks operations that only read state, which is all a
+/// [`crate::ReadOnlyClient`] can send.
+pub trait ReadOperation: Operation {}
+
+impl ReadOperation for EstimateWithdrawal {}
+impl ReadOperation for GetLightningInvoice {}
+impl ReadOperation for GetLightningInvoiceByHash {}
+impl ReadOperation for ListLightningInvoices {}
+impl ReadOperation for ListLightningAddresses {}
+impl ReadOperation for GetLightningAddress {}
+impl ReadOperation for GetLightningAddressByHandle {}
+impl ReadOperation for ListLightningAddressDeposits {}
+impl ReadOperation for ListOnChainAddresses {}
+impl ReadOperation for GetOnChainAddress {}
+impl ReadOperation for GetOnChainAddressByAddress {}
+impl ReadOperation for ListOnChainAddressDeposits {}
+impl ReadOperation for GetWithdrawal {}
+impl ReadOperation for GetDeposit {}
+impl ReadOperation for ListWithdrawals {}
+impl ReadOperation for ListDeposits {}
+impl ReadOperation for GetBalance {}
+impl ReadOperation for GetTransactions {}
+impl ReadOperation for ListTransactions {}
+impl ReadOperation for GetAccount {}
+impl ReadOperation for GetCurrentApiKey {}
+
+pub struct Withdraw(pub WithdrawRequest);
+
+impl Operation for Withdraw {
+    type Response = WithdrawResponse;
+    const NAME: &'static str = "withdraw";
+
+    fn method(&self) -> Method {
+        Method::POST
+    }
+
+    fn path(&self) -> String {
+        "/v1/bitcoin/withdraw/confirm".to_string()
+    }
+
+    fn body(&self) -> Result<Option<Vec<u8>>, serde_json::Error> {
+        serde_json::to_vec(&self.0).map(Some)
+    }
+
+    #[cfg(feature = "tracing")]
+    fn record(&self, span: &tracing::Span) {
+        span.record("destination", crate::trace::redact(&self.0.destination));
+    }
+
+    #[cfg(feature = "tracing")]
+    fn record_response(response: &Self::Response, span: &tracing::Span) {
+        span.record("withdrawal_id", response.data.id.as_str());
+    }
+
+    #[cfg(feature = "metrics")]
+    fn record_metrics(response: &Self::Response) {
+        use crate::telemetry::{FEES_PAID_MSATS_TOTAL, WITHDRAWN_SATS_TOTAL};
+
+        metrics::counter!(WITHDRAWN_SATS_TOTAL).increment(response.data.amount_sats);
+        metrics::counter!(FEES_PAID_MSATS_TOTAL)
+            .increment((response.data.fee_sats * 1000.0).round() as u64);
+    }
+}
+
+pub struct EstimateWithdrawal(pub EstimateWithdrawalRequest);
+
+impl Operation for EstimateWithdrawal {
+    type Response = EstimateWithdrawalResponse;
+    const NAME: &'static str = "estimate_withdrawal";
+
+    fn method(&self) -> Method {
+        Method::POST
+    }
+
+    fn path(&self) -> String {
+        "/v1/bitcoin/withdraw/estimate".to_string()
+    }
+
+    fn body(&self) -> Result<Option<Vec<u8>>, serde_json::Error> {
+        serde_json::to_vec(&self.0).map(Some)
+    }
+
+    #[cfg(feature = "tracing")]
+    fn record(&self, span: &tracing::Span) {
+        span.record("destination", crate::trace::redact(&self.0.destination));
+    }
+}
+
+pub struct CreateLightningInvoice(pub CreateLightningInvoiceRequest);
+
+impl Operation for CreateLightningInvoice {
+    type Response = CreateLightningInvoiceResponse;
+    const NAME: &'static str = "create_lightning_invoice";
+
+    fn method(&self) -> Method {
+        Method::POST
+    }
+
+    fn path(&self) -> String {
+        "/v1/bitcoin/deposit/lightning-invoice".to_string()
+    }
+
+    fn body(&self) -> Result<Option<Vec<u8>>, serde_json::Error> {
+        serde_json::to_vec(&self.0).map(Some)
+    }
+
+    #[cfg(feature = "tracing")]
+    fn record_response(response: &Self::Response, span: &tracing::Span) {
+        span.record("invoice_id", response.data.id.as_str());
+    }
+
+    #[cfg(feature = "metrics")]
+    fn record_metrics(_response: &Self::Response) {
+        metrics::counter!(crate::telemetry::INVOICES_CREATED_TOTAL).increment(1);
+    }
+}
+
+pub struct GetLightningInvoice {
+    pub id: String,
+}
+
+impl Operation for GetLightningInvoice {
+    type Response = CreateLightningInvoiceResponse;
+    const NAME: &'static str = "get_lightning_invoice";
+
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn path(&self) -> String {
+        format!(
+            "/v1/bitcoin/deposit/lightning-invoice/{}",
+            segment(&self.id)
+        )
+    }
+
+    #[cfg(feature = "tracing")]
+    fn record(&self, span: &tracing::Span) {
+        span.record("invoice_id", self.id.as_str());
+    }
+}
+
+pub struct GetLightningInvoiceByHash {
+    pub r_hash: String,
+}
+
+impl Operation for GetLightningInvoiceByHash {
+    type Response = CreateLightningInvoiceResponse;
+    const NAME: &'static str = "get_lightning_invoice_by_hash";
+
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn path(&self) -> String {
+        "/v1/bitcoin/deposit/lightning-invoice/by-hash".to_string()
+    }
+
+    // Sent as a parameter since base64 hashes may contain `/`.
+    fn query(&self) -> Vec<(String, String)> {
+        vec![("r_hash".to_string(), self.r_hash.clone())]
+    }
+}
+
+pub struct ListLightningInvoices(pub LightningInvoiceQuery);
+
+impl Operation for ListLightningInvoices {
+    type Response = ListLightningInvoicesResponse;
+    const NAME: &'static str = "list_lightning_invoices";
+
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn path(&self) -> String {
+        "/v1/bitcoin/deposit/lightning-invoice".to_string()
+    }
+
+    fn query(&self) -> Vec<(String, String)> {
+        self.0.to_pairs()
+    }
+}
+
+pub struct CreateLightningAddress(pub CreateLightningAddressRequest);
+
+impl Operation for CreateLightningAddress {
+    type Response = CreateLightningAddressResponse;
+    const NAME: &'static str = "create_lightning_address";
+
+    fn method(&self) -> Method {
+        Method::POST
+    }
+
+    fn path(&self) -> String {
+        "/v1/bitcoin/deposit/lightning-address".to_string()
+    }
+
+    fn body(&self) -> Result<Option<Vec<u8>>, serde_json::Error> {
+        serde_json::to_vec(&self.0).map(Some)
+    }
+}
+
+pub struct ListLightningAddresses;
+
+impl Operation for ListLightningAddresses {
+    type Response = ListLightningAddressesResponse;
+    const NAME: &'static str = "list_lightning_addresses";
+
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn path(&self) -> String {
+        "/v1/bitcoin/deposit/lightning-address".to_string()
+    }
+}
+
+pub struct GetLightningAddress {
+    pub id: String,
+}
+
+impl Operation for GetLightningAddress {
+    type Response = CreateLightningAddressResponse;
+    const NAME: &'static str = "get_lightning_address";
+
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn path(&self) -> String {
+        format!(
+            "/v1/bitcoin/deposit/lightning-address/{}",
+            segment(&self.id)
+        )
+    }
+}
+
+pub struct GetLightningAddressByHandle {
+    pub handle: String,
+}
+
+impl Operation for GetLightningAddressByHandle {
+    type Response = CreateLightningAddressResponse;
+    const NAME: &'static str = "get_lightning_address_by_handle";
+
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn path(&self) -> String {
+        format!(
+            "/v1/bitcoin/deposit/lightning-address/handle/{}",
+            segment(&self.handle)
+        )
+    }
+}
+
+pub struct UpdateLightningAddress {
+    pub id: String,
+    pub request: UpdateLightningAddressRequest,
+}
+
+impl Operation for UpdateLightningAddress {
+    type Response = CreateLightningAddressResponse;
+    const NAME: &'static str = "update_lightning_address";
+
+    fn method(&self) -> Method {
+        Method::PATCH
+    }
+
+    fn path(&self) -> String {
+        format!(
+            "/v1/bitcoin/deposit/lightning-address/{}",
+            segment(&self.id)
+        )
+    }
+
+    fn body(&self) -> Result<Option<Vec<u8>>, serde_json::Error> {
+        serde_json::to_vec(&self.request).map(Some)
+    }
+}
+
+pub struct DeleteLightningAddress {
+    pub id: String,
+}
+
+impl Operation for DeleteLightningAddress {
+    type Response = DeleteLightningAddressResponse;
+    const NAME: &'static str = "delete_lightning_address";
+
+    fn method(&self) -> Method {
+        Method::DELETE
+    }
+
+    fn path(&self) -> String {
+        format!(
+            "/v1/bitcoin/deposit/lightning-address/{}",
+            segment(&self.id)
+        )
+    }
+}
+
+pub struct ListLightningAddressDeposits {
+    pub id: String,
+}
+
+impl Operation for ListLightningAddressDeposits {
+    type Response = ListDepositsResponse;
+    const NAME: &'static str = "list_lightning_address_deposits";
+
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn path(&self) -> String {
+        format!(
+            "/v1/bitcoin/deposit/lightning-address/{}/deposits",
+            segment(&self.id)
+        )
+    }
+}
+
+pub struct CreateOnChainAddress(pub CreateOnChainAddressRequest);
+
+impl Operation for CreateOnChainAddress {
+    type Response = CreateOnChainAddressResponse;
+    const NAME: &'static str = "create_onchain_address";
+
+    fn method(&self) -> Method {
+        Method::POST
+    }
+
+    fn path(&self) -> String {
+        "/v1/bitcoin/deposit/on-chain".to_string()
+    }
+
+    fn body(&self) -> Result<Option<Vec<u8>>, serde_json::Error> {
+        serde_json::to_vec(&self.0).map(Some)
+    }
+}
+
+pub struct ListOnChainAddresses;
+
+impl Operation for ListOnChainAddresses {
+    type Response = ListOnChainAddressesResponse;
+    const NAME: &'static str = "list_onchain_addresses";
+
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn path(&self) -> String {
+        "/v1/bitcoin/deposit/on-chain".to_string()
+    }
+}
+
+pub struct GetOnChainAddress {
+    pub id: String,
+}
+
+impl Operation for GetOnChainAddress {
+    type Response = CreateOnChainAddressResponse;
+    const NAME: &'static str = "get_onchain_address";
+
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn path(&self) -> String {
+        format!("/v1/bitcoin/deposit/on-chain/{}", segment(&self.id))
+    }
+}
+
+pub struct GetOnChainAddressByAddress {
+    pub address: String,
+}
+
+impl Operation for GetOnChainAddressByAddress {
+    type Response = CreateOnChainAddressResponse;
+    const NAME: &'static str = "get_onchain_address_by_address";
+
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn path(&self) -> String {
+        format!(
+            "/v1/bitcoin/deposit/on-chain/address/{}",
+            segment(&self.address)
+        )
+    }
+}
+
+pub struct ListOnChainAddressDeposits {
+    pub id: String,
+}
+
+impl Operation for ListOnChainAddressDeposits {
+    type Response = ListDepositsResponse;
+    const NAME: &'static str = "list_onchain_address_deposits";
+
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn path(&self) -> String {
+        format!(
+            "/v1/bitcoin/deposit/on-chain/{}/deposits",
+            segment(&self.id)
+        )
+    }
+}
+
+pub struct GetWithdrawal {
+    pub id: String,
+}
+
+impl Operation for GetWithdrawal {
+    type Response = WithdrawResponse;
+    const NAME: &'static str = "get_withdrawal";
+
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn path(&self) -> String {
+        format!("/v1/transactions/withdrawals/{}", segment(&self.id))
+    }
+
+    #[cfg(feature = "tracing")]
+    fn record(&self, span: &tracing::Span) {
+        span.record("withdrawal_id", self.id.as_str());
+    }
+}
+
+pub struct GetDeposit {
+    pub id: String,
+}
+
+impl Operation for GetDeposit {
+    type Response = GetDepositResponse;
+    const NAME: &'static str = "get_deposit";
+
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn path(&self) -> String {
+        format!("/v1/transactions/deposits/{}", segment(&self.id))
+    }
+
+    #[cfg(feature = "tracing")]
+    fn record(&self, span: &tracing::Span) {
+        span.record("deposit_id", self.id.as_str());
+    }
+}
+
+pub struct ListWithdrawals(pub PaymentQuery);
+
+impl Operation for ListWithdrawals {
+    type Response = ListWithdrawalsResponse;
+    const NAME: &'static str = "list_withdrawals";
+
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn path(&self) -> String {
+        "/v1/transactions/withdrawals".to_string()
+    }
+
+    fn query(&self) -> Vec<(String, String)> {
+        self.0.to_pairs()
+    }
+}
+
+pub struct ListDeposits(pub PaymentQuery);
+
+impl Operation for ListDeposits {
+    type Response = ListDepositsResponse;
+    const NAME: &'static str = "list_deposits";
+
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn path(&self) -> String {
+        "/v1/transactions/deposits".to_string()
+    }
+
+    fn query(&self) -> Vec<(String, String)> {
+        self.0.to_pairs()
+    }
+}
+
+pub struct GetBalance;
+
+impl Operation for GetBalance {
+    type Response = GetBalanceResponse;
+    const NAME: &'static str = "get_balance";
+
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn path(&self) -> String {
+        "/v1/transactions/balance".to_string()
+    }
+}
+
+pub struct GetTransactions;
+
+impl Operation for GetTransactions {
+    type Response = GetTransactionsResponse;
+    const NAME: &'static str = "get_transactions";
+
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn path(&self) -> String {
+        "/v1/transactions".to_string()
+    }
+}
+
+pub struct ListTransactions(pub TransactionQuery);
+
+impl Operation for ListTransactions {
+    type Response = ListTransactionsResponse;
+    const NAME: &'static str = "list_transactions";
+
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn path(&self) -> String {
+        "/v1/transactions".to_string()
+    }
+
+    fn query(&self) -> Vec<(String, String)> {
+        self.0.to_pairs()
+    }
+}
+
+pub struct CreateWebhook(pub CreateWebhookRequest);
+
+impl Operation for CreateWebhook {
+    type Response = WebhookResponse;
+    const NAME: &'static str = "create_webhook";
+
+    fn method(&self) -> Method {
+        Method::POST
+    }
+
+    fn path(&self) -> String {
+        "/v1/webhooks".to_string()
+    }
+
+    fn body(&self) -> Result<Option<Vec<u8>>, serde_json::Error> {
+        serde_json::to_vec(&self.0).map(Some)
+    }
+}
+
+pub struct ListWebhooks;
+
+impl Operation for ListWebhooks {
+    type Response = ListWebhooksResponse;
+    const NAME: &'static str = "list_webhooks";
+
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn path(&self) -> String {
+        "/v1/webhooks".to_string()
+    }
+}
+
+pub struct UpdateWebhook {
+    pub id: String,
+    pub request: UpdateWebhookRequest,
+}
+
+impl Operation for UpdateWebhook {
+    type Response = WebhookResponse;
+    const NAME: &'static str = "update_webhook";
+
+    fn method(&self) -> Method {
+        Method::PATCH
+    }
+
+    fn path(&self) -> String {
+        format!("/v1/webhooks/{}", segment(&self.id))
+    }
+
+    fn body(&self) -> Result<Option<Vec<u8>>, serde_json::Error> {
+        serde_json::to_vec(&self.request).map(Some)
+    }
+}
+
+pub struct DeleteWebhook {
+    pub id: String,
+}
+
+impl Operation for DeleteWebhook {
+    type Response = DeleteWebhookResponse;
+    const NAME: &'static str = "delete_webhook";
+
+    fn method(&self) -> Method {
+        Method::DELETE
+    }
+
+    fn path(&self) -> String {
+        format!("/v1/webhooks/{}", segment(&self.id))
+    }
+}
+
+pub struct RotateWebhookSecret {
+    pub id: String,
+}
+
+impl Operation for RotateWebhookSecret {
+    type Response = WebhookResponse;
+    const NAME: &'static str = "rotate_webhook_secret";
+
+    fn method(&self) -> Method {
+        Method::POST
+    }
+
+    fn path(&self) -> String {
+        format!("/v1/webhooks/{}/rotate-secret", segment(&self.id))
+    }
+}
+
+pub struct SendTestWebhook {
+    pub id: String,
+    pub request: SendTestWebhookRequest,
+}
+
+impl Operation for SendTestWebhook {
+    type Response = SendTestWebhookResponse;
+    const NAME: &'static str = "send_test_webhook";
+
+    fn method(&self) -> Method {
+        Method::POST
+    }
+
+    fn path(&self) -> String {
+        format!("/v1/webhooks/{}/test", segment(&self.id))
+    }
+
+    fn body(&self) -> Result<Option<Vec<u8>>, serde_json::Error> {
+        serde_json::to_vec(&self.request).map(Some)
+    }
+}
+
+pub struct GetAccount;
+
+impl Operation for GetAccount {
+    type Response = AccountResponse;
+    const NAME: &'static str = "get_account";
+
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn path(&self) -> String {
+        "/v1/account".to_string()
+    }
+}
+
+pub struct GetCurrentApiKey;
+
+impl Operation for GetCurrentApiKey {
+    type Response = ApiKeyResponse;
+    const NAME: &'static str = "get_current_api_key";
+
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn path(&self) -> String {
+        "/v1/api-keys/current".to_string()
+    }
+}
+
+pub struct CreateApiKey(pub CreateApiKeyRequest);
+
+impl Operation for CreateApiKey {
+    type Response = ApiKeyResponse;
+    const NAME: &'static str = "create_api_key";
+
+    fn method(&self) -> Method {
+        Method::POST
+    }
+
+    fn path(&self) -> String {
+        "/v1/api-keys".to_string()
+    }
+
+    fn body(&self) -> Result<Option<Vec<u8>>, serde_json::Error> {
+        serde_json::to_vec(&self.0).map(Some)
+    }
+}
+
+pub struct ListApiKeys;
+
+impl Operation for ListApiKeys {
+    type Response = ListApiKeysResponse;
+    const NAME: &'static str = "list_api_keys";
+
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn path(&self) -> String {
+        "/v1/api-keys".to_string()
+    }
+}
+
+pub struct RevokeApiKey {
+    pub id: String,
+}
+
+impl Operation for RevokeApiKey {
+    type Response = RevokeApiKeyResponse;
+    const NAME: &'static str = "revoke_api_key";
+
+    fn method(&self) -> Method {
+        Method::DELETE
+    }
+
+    fn path(&self) -> String {
+        format!("/v1/api-keys/{}", segment(&self.id))
+    }
+}
+
+impl<O: Operation> Service<O> for BitvoraClient {
+    type Response = O::Response;
+    type Error = APIError;
+    type Future = ResponseFuture<O::Response>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        // Readiness of the underlying transport is awaited per call.
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, operation: O) -> Self::Future {
+        let client = self.clone();
+        Box::pin(async move { client.execute(operation).await })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{ApiKeyScope, Pagination};
+    use crate::secret::ApiKey;
+    use crate::test_support::{self, client, deposit, transaction, with, withdrawal, Reply};
+    use serde_json::json;
+    use std::time::Duration;
+    use tower::{ServiceBuilder, ServiceExt};
+
+    #[tokio::test]
+    async fn test_requests_are_sent_through_transport() {
+        let (client, requests) = client(|_| Reply::ok(json!({"balance": 42})));
+
+        let response = client.get_balance().await.unwrap();
+
+        assert_eq!(response.data.balance, 42);
+        let request = &requests.all()[0];
+        assert_eq!(request.method, Method::GET);
+        assert_eq!(
+            request.url.as_str(),
+            "https://api.example.com/v1/transactions/balance"
+        );
+        assert_eq!(request.authorization.as_deref(), Some("Bearer secret"));
+    }
+
+    #[tokio::test]
+    async fn test_client_is_a_service_per_operation() {
+        let (client, requests) = client(|_| Reply::status(404, "not found"));
+
+        let result = client
+            .oneshot(GetDeposit {
+                id: "abc".to_string(),
+            })
+            .await;
+
+        match result {
+            Err(APIError::BadRequest(status, body)) => {
+                assert_eq!(status.as_u16(), 404);
+                assert_eq!(body, "not found");
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert_eq!(requests.targets(), ["/v1/transactions/deposits/abc"]);
+    }
+
+    #[tokio::test]
+    async fn test_ids_are_encoded_as_one_path_segment() {
+        let (client, requests) = client(|_| Reply::status(404, "not found"));
+
+        client.get_withdrawal("a/b?c#d").await.unwrap_err();
+        client
+            .get_lightning_address_by_handle("../../webhooks")
+            .await
+            .unwrap_err();
+        for id in ["", ".", ".."] {
+            assert!(matches!(
+                client.delete_webhook(id).await,
+                Err(APIError::InvalidPath(_))
+            ));
+        }
+
+        assert_eq!(
+            requests.targets(),
+            [
+                "/v1/transactions/withdrawals/a%2Fb%3Fc%23d",
+                "/v1/bitcoin/deposit/lightning-address/handle/..%2F..%2Fwebhooks",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_webhook_management_requests() {
+        let (client, requests) = client(|_| {
+            Reply::ok(json!({
+                "id": "wh1",
+                "url": "https://example.com/hook",
+                "events": ["withdrawal.completed"],
+                "description": null,
+                "enabled": false,
+                "secret": "whsec_new",
+                "created_at": "2024-01-01T00:00:00Z",
+                "updated_at": null,
+            }))
+        });
+
+        client
+            .update_webhook(
+                "wh1",
+                UpdateWebhookRequest {
+                    enabled: Some(false),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        let rotated = client.rotate_webhook_secret("wh1").await.unwrap();
+
+        assert_eq!(
+            rotated.data.secret.as_ref().map(ApiKey::expose),
+            Some("whsec_new")
+        );
+        assert!(!format!("{:?}", rotated).contains("whsec_new"));
+        let requests = requests.all();
+        assert_eq!(
+            (requests[0].method.clone(), requests[0].target()),
+            (Method::PATCH, "/v1/webhooks/wh1".to_string())
+        );
+        assert_eq!(requests[0].body.as_deref(), Some(r#"{"enabled":false}"#));
+        assert_eq!(
+            (requests[1].method.clone(), requests[1].target()),
+            (Method::POST, "/v1/webhooks/wh1/rotate-secret".to_string())
+        );
+        assert_eq!(requests[1].body, None);
+    }
+
+    #[tokio::test]
+    async fn test_lightning_address_requests() {
+        let (client, requests) = client(|request| {
+            if request.path().ends_with("/deposits") {
+                Reply::ok(json!([with(
+                    deposit("d1", "completed"),
+                    json!({"recipient": "alice@example.com", "lightning_invoice_id": "i1"}),
+                )]))
+            } else {
+                Reply::ok(json!({
+                    "id": "la1",
+                    "handle": "alice",
+                    "domain": "example.com",
+                    "address": "alice@example.com",
+                    "metadata": {"user": "7"},
+                    "created_at": "2024-01-01T00:00:00Z",
+                    "last_used_at": null,
+                    "deleted_at": null,
+                }))
+            }
+        });
+
+        let address = client
+            .get_lightning_address_by_handle("alice")
+            .await
+            .unwrap();
+        client
+            .update_lightning_address(
+                &address.data.id,
+                UpdateLightningAddressRequest {
+                    metadata: [("user".to_string(), "7".to_string())].into(),
+                },
+            )
+            .await
+            .unwrap();
+        let deposits = client
+            .list_lightning_address_deposits(&address.data.id)
+            .await
+            .unwrap();
+
+        assert_eq!(address.data.address, "alice@example.com");
+        assert_eq!(deposits.data[0].lightning_invoice_id.as_deref(), Some("i1"));
+        let methods: Vec<_> = requests.all().into_iter().map(|r| r.method).collect();
+        assert_eq!(methods, [Method::GET, Method::PATCH, Method::GET]);
+        assert_eq!(
+            requests.targets(),
+            [
+                "/v1/bitcoin/deposit/lightning-address/handle/alice",
+                "/v1/bitcoin/deposit/lightning-address/la1",
+                "/v1/bitcoin/deposit/lightning-address/la1/deposits",
+            ]
+        );
+        assert_eq!(requests.bodies(), [r#"{"metadata":{"user":"7"}}"#]);
+    }
+
+    #[tokio::test]
+    async fn test_list_transactions_sends_filters_and_pages() {
+        let (client, requests) = client(|_| {
+            Reply::json(json!({
+                "status": 200,
+                "message": "ok",
+                "data": [transaction("t1", "deposit", "completed")],
+                "pagination": {"has_more": true, "next_cursor": "abc"},
+            }))
+        });
+
+        let query = TransactionQuery {
+            limit: Some(1),
+            created_after: Some("2024-01-01T00:00:00Z".to_string()),
+            r#type: Some("deposit".to_string()),
+            metadata: [("order".to_string(), "42".to_string())].into(),
+            ..Default::default()
+        };
+        let page = client.list_transactions(query.clone()).await.unwrap();
+        let next = page.next_query(&query).unwrap();
+
+        assert_eq!(page.data.len(), 1);
+        assert_eq!(next.cursor.as_deref(), Some("abc"));
+        client.get_transactions().await.unwrap();
+        assert_eq!(
+            requests.targets(),
+            [
+                "/v1/transactions?limit=1&created_after=2024-01-01T00%3A00%3A00Z&type=deposit&metadata%5Border%5D=42",
+                "/v1/transactions",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_onchain_address_lookups() {
+        let (client, requests) = client(|request| {
+            if request.path().ends_with("/deposits") {
+                Reply::ok(json!([with(
+                    deposit("d1", "completed"),
+                    json!({"recipient": "tb1qabc", "chain_tx_id": "tx1", "rail_type": "onchain"}),
+                )]))
+            } else {
+                Reply::ok(json!({
+                    "id": "oa1",
+                    "address": "tb1qabc",
+                    "metadata": {"customer": "7"},
+                    "created_at": "2024-01-01T00:00:00Z",
+                }))
+            }
+        });
+
+        let address = client
+            .get_onchain_address_by_address("tb1qabc")
+            .await
+            .unwrap()
+            .data;
+        let deposits = client
+            .list_onchain_address_deposits(&address.id)
+            .await
+            .unwrap();
+
+        assert_eq!(address.metadata.unwrap()["customer"], "7");
+        assert_eq!(deposits.data[0].chain_tx_id.as_deref(), Some("tx1"));
+        assert_eq!(
+            requests.targets(),
+            [
+                "/v1/bitcoin/deposit/on-chain/address/tb1qabc",
+                "/v1/bitcoin/deposit/on-chain/oa1/deposits",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_lightning_invoice_lookups() {
+        let invoice = with(
+            test_support::lightning_invoice("i1", true),
+            json!({"r_hash": "ab/c+=", "lightning_address_id": "la1"}),
+        );
+        let (client, requests) = client(move |request| {
+            if request.path().ends_with("/by-hash") {
+                Reply::ok(invoice.clone())
+            } else {
+                Reply::ok(json!([invoice.clone()]))
+            }
+        });
+
+        let invoice = client
+            .get_lightning_invoice_by_hash("ab/c+=")
+            .await
+            .unwrap();
+        let listed = client
+            .list_lightning_invoices(LightningInvoiceQuery {
+                settled: Some(true),
+                lightning_address_id: Some("la1".to_string()),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(invoice.data.id, "i1");
+        assert_eq!(listed.data.len(), 1);
+        assert!(!listed.pagination.has_more);
+        assert_eq!(
+            requests.targets(),
+            [
+                "/v1/bitcoin/deposit/lightning-invoice/by-hash?r_hash=ab%2Fc%2B%3D",
+                "/v1/bitcoin/deposit/lightning-invoice?settled=true&lightning_address_id=la1",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_withdrawals_and_deposits_with_filters() {
+        let (client, requests) = client(|request| {
+            if request.path().ends_with("/withdrawals") {
+                Reply::json(json!({
+                    "status": 200,
+                    "message": "ok",
+                    "data": [with(
+                        withdrawal("w1", "completed"),
+                        json!({"recipient": "alice@example.com", "metadata": {"payout": "9"}}),
+                    )],
+                    "pagination": {"has_more": false},
+                }))
+            } else {
+                Reply::ok(json!([]))
+            }
+        });
+
+        let query = PaymentQuery {
+            status: Some("completed".to_string()),
+            recipient: Some("alice@example.com".to_string()),
+            metadata: [("payout".to_string(), "9".to_string())].into(),
+            ..Default::default()
+        };
+        let withdrawals = client.list_withdrawals(query.clone()).await.unwrap();
+        let deposits = client.list_deposits(query).await.unwrap();
+
+        assert_eq!(
+            withdrawals.data[0].metadata.as_ref().unwrap()["payout"],
+            "9"
+        );
+        assert!(deposits.data.is_empty());
+        let filters = "status=completed&recipient=alice%40example.com&metadata%5Bpayout%5D=9";
+        assert_eq!(
+            requests.targets(),
+            [
+                format!("/v1/transactions/withdrawals?{filters}"),
+                format!("/v1/transactions/deposits?{filters}"),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_create_api_key_with_scopes() {
+        let (client, requests) = client(|_| {
+            Reply::status(
+                201,
+                json!({
+                    "status": 201,
+                    "message": "created",
+                    "data": {
+                        "id": "k1",
+                        "name": "reporting",
+                        "scopes": ["read", "export"],
+                        "key": "bv_live_abc",
+                        "created_at": "2024-01-01T00:00:00Z",
+                        "last_used_at": null,
+                        "revoked_at": null,
+                    },
+                })
+                .to_string(),
+            )
+        });
+
+        let key = client
+            .create_api_key(CreateApiKeyRequest {
+                name: "reporting".to_string(),
+                scopes: vec![ApiKeyScope::Read],
+            })
+            .await
+            .unwrap()
+            .data;
+
+        assert_eq!(
+            requests.bodies(),
+            [r#"{"name":"reporting","scopes":["read"]}"#]
+        );
+        assert!(key.has_scope(&ApiKeyScope::Read));
+        assert!(!key.has_scope(&ApiKeyScope::Withdraw));
+        assert_eq!(key.scopes[1], ApiKeyScope::Other("export".to_string()));
+        assert_eq!(key.key.as_ref().unwrap().expose(), "bv_live_abc");
+        assert!(!format!("{:?}", key).contains("bv_live_abc"));
+    }
+
+    #[test]
+    fn test_next_query_advances_offset_without_cursor() {
+        let page: ListTransactionsResponse = serde_json::from_value(json!({
+            "status": 200,
+            "message": "ok",
+            "data": [transaction("t1", "deposit", "completed")],
+            "pagination": {"has_more": true},
+        }))
+        .unwrap();
+        let query = TransactionQuery {
+            offset: Some(10),
+            ..Default::default()
+        };
+
+        assert_eq!(page.next_query(&query).unwrap().offset, Some(11));
+
+        let unpaged: ListTransactionsResponse =
+            serde_json::from_str(r#"{"status":200,"message":"ok","data":[]}"#).unwrap();
+        assert_eq!(unpaged.pagination, Pagination::default());
+        assert!(unpaged.next_query(&query).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_layer_errors_surface_as_transport_errors() {
+        let (stub, _) =
+            test_support::transport(|_| Reply::ok(json!({})).after(Duration::from_secs(5)));
+        let transport = ServiceBuilder::new()
+            .timeout(Duration::from_millis(10))
+            .service(stub);
+        let client = BitvoraClient::with_transport(test_support::BASE_URL, "secret", transport);
+
+        match client.get_transactions().await {
+            Err(APIError::Transport(err)) => assert!(err.is::<tower::timeout::error::Elapsed>()),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+}
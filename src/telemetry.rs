@@ -0,0 +1,177 @@
+//! Metrics for API calls, enabled with the `metrics` feature.
+//!
+//! Metrics are emitted through the [`metrics`] facade, so they reach whichever
+//! recorder the application installs. The `prometheus` feature adds
+//! [`install_prometheus_recorder`] and the `opentelemetry` feature adds
+//! [`OpenTelemetryRecorder`] for applications that don't already have one.
+
+use crate::errors::APIError;
+use crate::service::Operation;
+use metrics::{counter, describe_counter, describe_histogram, histogram, Unit};
+use std::time::Duration;
+
+#[cfg(feature = "opentelemetry")]
+mod otel;
+#[cfg(feature = "opentelemetry")]
+pub use otel::OpenTelemetryRecorder;
+
+pub const REQUESTS_TOTAL: &str = "bitvora_requests_total";
+pub const ERRORS_TOTAL: &str = "bitvora_errors_total";
+pub const REQUEST_DURATION_SECONDS: &str = "bitvora_request_duration_seconds";
+pub const WITHDRAWN_SATS_TOTAL: &str = "bitvora_withdrawn_sats_total";
+pub const FEES_PAID_MSATS_TOTAL: &str = "bitvora_fees_paid_msats_total";
+pub const INVOICES_CREATED_TOTAL: &str = "bitvora_invoices_created_total";
+
+/// Registers units and descriptions for every metric the SDK emits.
+///
+/// Call it once after installing a recorder; the exporters in this module do so
+/// themselves.
+pub fn describe_metrics() {
+    describe_counter!(REQUESTS_TOTAL, "Bitvora API calls, by operation");
+    describe_counter!(
+        ERRORS_TOTAL,
+        "Failed Bitvora API calls, by operation and error kind"
+    );
+    describe_histogram!(
+        REQUEST_DURATION_SECONDS,
+        Unit::Seconds,
+        "Latency of Bitvora API calls, by operation"
+    );
+    describe_counter!(
+        WITHDRAWN_SATS_TOTAL,
+        "Sats sent through confirmed withdrawals"
+    );
+    describe_counter!(
+        FEES_PAID_MSATS_TOTAL,
+        "Bitvora fees charged on confirmed withdrawals, in millisats"
+    );
+    describe_counter!(INVOICES_CREATED_TOTAL, "Lightning invoices created");
+}
+
+pub(crate) fn record_call<O: Operation>(elapsed: Duration, result: &Result<O::Response, APIError>) {
+    counter!(REQUESTS_TOTAL, "operation" => O::NAME).increment(1);
+    histogram!(REQUEST_DURATION_SECONDS, "operation" => O::NAME).record(elapsed.as_secs_f64());
+
+    match result {
+        Ok(response) => O::record_metrics(response),
+        Err(err) => {
+            counter!(ERRORS_TOTAL, "operation" => O::NAME, "kind" => err.kind()).increment(1)
+        }
+    }
+}
+
+/// Installs a global Prometheus recorder and returns the handle used to render
+/// the text exposition format, e.g. from a `/metrics` endpoint.
+#[cfg(feature = "prometheus")]
+pub fn install_prometheus_recorder(
+) -> Result<metrics_exporter_prometheus::PrometheusHandle, metrics_exporter_prometheus::BuildError>
+{
+    let handle = metrics_exporter_prometheus::PrometheusBuilder::new().install_recorder()?;
+    describe_metrics();
+    Ok(handle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{GetBalanceData, GetBalanceResponse, WithdrawData, WithdrawResponse};
+    use crate::service::{GetBalance, Withdraw};
+    use metrics::SharedString;
+    use metrics_util::debugging::{DebugValue, DebuggingRecorder};
+    use metrics_util::CompositeKey;
+    use reqwest::StatusCode;
+
+    fn withdraw_response() -> WithdrawResponse {
+        WithdrawResponse {
+            status: 201,
+            message: "ok".to_string(),
+            data: WithdrawData {
+                id: "w1".to_string(),
+                ledger_tx_id: None,
+                amount_sats: 2100,
+                recipient: "someone@bitvora.me".to_string(),
+                fee_sats: 1.5,
+                network_type: "signet".to_string(),
+                rail_type: "lightning".to_string(),
+                status: "settled".to_string(),
+                lightning_payment: None,
+                chain_tx_id: None,
+                metadata: None,
+                created_at: "2024-01-01T00:00:00Z".to_string(),
+            },
+        }
+    }
+
+    fn counter_value(
+        snapshot: &[(CompositeKey, Option<Unit>, Option<SharedString>, DebugValue)],
+        name: &str,
+    ) -> Option<u64> {
+        snapshot
+            .iter()
+            .find(|(key, ..)| key.key().name() == name)
+            .and_then(|(.., value)| match value {
+                DebugValue::Counter(value) => Some(*value),
+                _ => None,
+            })
+    }
+
+    #[test]
+    fn test_successful_withdrawal_records_business_metrics() {
+        let recorder = DebuggingRecorder::new();
+        metrics::with_local_recorder(&recorder, || {
+            record_call::<Withdraw>(Duration::from_millis(20), &Ok(withdraw_response()));
+        });
+
+        let snapshot = recorder.snapshotter().snapshot().into_vec();
+        assert_eq!(counter_value(&snapshot, REQUESTS_TOTAL), Some(1));
+        assert_eq!(counter_value(&snapshot, WITHDRAWN_SATS_TOTAL), Some(2100));
+        assert_eq!(counter_value(&snapshot, FEES_PAID_MSATS_TOTAL), Some(1500));
+        assert_eq!(counter_value(&snapshot, ERRORS_TOTAL), None);
+    }
+
+    #[test]
+    fn test_errors_are_counted_by_kind() {
+        let recorder = DebuggingRecorder::new();
+        metrics::with_local_recorder(&recorder, || {
+            let result: Result<GetBalanceResponse, APIError> = Err(APIError::BadRequest(
+                StatusCode::UNAUTHORIZED,
+                String::new(),
+            ));
+            record_call::<GetBalance>(Duration::from_millis(5), &result);
+            record_call::<GetBalance>(
+                Duration::from_millis(5),
+                &Ok(GetBalanceResponse {
+                    status: 200,
+                    message: "ok".to_string(),
+                    data: GetBalanceData { balance: 1 },
+                }),
+            );
+        });
+
+        let snapshot = recorder.snapshotter().snapshot().into_vec();
+        let (key, ..) = snapshot
+            .iter()
+            .find(|(key, ..)| key.key().name() == ERRORS_TOTAL)
+            .unwrap();
+        assert!(key
+            .key()
+            .labels()
+            .any(|label| label.key() == "kind" && label.value() == "bad_request"));
+        assert_eq!(counter_value(&snapshot, REQUESTS_TOTAL), Some(2));
+    }
+
+    #[cfg(feature = "prometheus")]
+    #[test]
+    fn test_prometheus_text_format() {
+        let recorder = metrics_exporter_prometheus::PrometheusBuilder::new().build_recorder();
+        let handle = recorder.handle();
+        metrics::with_local_recorder(&recorder, || {
+            describe_metrics();
+            record_call::<Withdraw>(Duration::from_millis(20), &Ok(withdraw_response()));
+        });
+
+        let rendered = handle.render();
+        assert!(rendered.contains("bitvora_requests_total{operation=\"withdraw\"} 1"));
+        assert!(rendered.contains("bitvora_withdrawn_sats_total 2100"));
+    }
+}
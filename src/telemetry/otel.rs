@@ -0,0 +1,247 @@
+use metrics::{
+    Counter, CounterFn, Gauge, GaugeFn, Histogram, HistogramFn, Key, KeyName, Metadata, Recorder,
+    SharedString, Unit,
+};
+use opentelemetry::metrics::Meter;
+use opentelemetry::KeyValue;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// A [`metrics::Recorder`] that forwards everything to an OpenTelemetry
+/// [`Meter`], for applications that export through OpenTelemetry.
+///
+/// ```ignore
+/// let meter = opentelemetry::global::meter("bitvora");
+/// metrics::set_global_recorder(OpenTelemetryRecorder::new(meter))?;
+/// bitvora::telemetry::describe_metrics();
+/// ```
+pub struct OpenTelemetryRecorder {
+    meter: Meter,
+    descriptions: Mutex<HashMap<String, (Option<Unit>, SharedString)>>,
+    counters: Mutex<HashMap<String, opentelemetry::metrics::Counter<u64>>>,
+    histograms: Mutex<HashMap<String, opentelemetry::metrics::Histogram<f64>>>,
+    gauges: Mutex<HashMap<String, opentelemetry::metrics::Gauge<f64>>>,
+    gauge_values: Mutex<HashMap<Key, Arc<AtomicU64>>>,
+}
+
+impl OpenTelemetryRecorder {
+    pub fn new(meter: Meter) -> Self {
+        Self {
+            meter,
+            descriptions: Mutex::new(HashMap::new()),
+            counters: Mutex::new(HashMap::new()),
+            histograms: Mutex::new(HashMap::new()),
+            gauges: Mutex::new(HashMap::new()),
+            gauge_values: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn describe(&self, key: KeyName, unit: Option<Unit>, description: SharedString) {
+        self.descriptions
+            .lock()
+            .unwrap()
+            .insert(key.as_str().to_string(), (unit, description));
+    }
+
+    fn description(&self, name: &str) -> Option<(Option<Unit>, SharedString)> {
+        self.descriptions.lock().unwrap().get(name).cloned()
+    }
+}
+
+fn attributes(key: &Key) -> Vec<KeyValue> {
+    key.labels()
+        .map(|label| KeyValue::new(label.key().to_string(), label.value().to_string()))
+        .collect()
+}
+
+impl Recorder for OpenTelemetryRecorder {
+    fn describe_counter(&self, key: KeyName, unit: Option<Unit>, description: SharedString) {
+        self.describe(key, unit, description);
+    }
+
+    fn describe_gauge(&self, key: KeyName, unit: Option<Unit>, description: SharedString) {
+        self.describe(key, unit, description);
+    }
+
+    fn describe_histogram(&self, key: KeyName, unit: Option<Unit>, description: SharedString) {
+        self.describe(key, unit, description);
+    }
+
+    fn register_counter(&self, key: &Key, _metadata: &Metadata<'_>) -> Counter {
+        let name = key.name().to_string();
+        let counter = self
+            .counters
+            .lock()
+            .unwrap()
+            .entry(name.clone())
+            .or_insert_with(|| {
+                let mut builder = self.meter.u64_counter(name.clone());
+                if let Some((unit, description)) = self.description(&name) {
+                    builder = builder.with_description(description.into_owned());
+                    if let Some(unit) = unit {
+                        builder = builder.with_unit(unit.as_canonical_label());
+                    }
+                }
+                builder.build()
+            })
+            .clone();
+
+        Counter::from_arc(Arc::new(OtelCounter {
+            counter,
+            attributes: attributes(key),
+        }))
+    }
+
+    fn register_gauge(&self, key: &Key, _metadata: &Metadata<'_>) -> Gauge {
+        let name = key.name().to_string();
+        let gauge = self
+            .gauges
+            .lock()
+            .unwrap()
+            .entry(name.clone())
+            .or_insert_with(|| {
+                let mut builder = self.meter.f64_gauge(name.clone());
+                if let Some((unit, description)) = self.description(&name) {
+                    builder = builder.with_description(description.into_owned());
+                    if let Some(unit) = unit {
+                        builder = builder.with_unit(unit.as_canonical_label());
+                    }
+                }
+                builder.build()
+            })
+            .clone();
+        let value = self
+            .gauge_values
+            .lock()
+            .unwrap()
+            .entry(key.clone())
+            .or_default()
+            .clone();
+
+        Gauge::from_arc(Arc::new(OtelGauge {
+            gauge,
+            value,
+            attributes: attributes(key),
+        }))
+    }
+
+    fn register_histogram(&self, key: &Key, _metadata: &Metadata<'_>) -> Histogram {
+        let name = key.name().to_string();
+        let histogram = self
+            .histograms
+            .lock()
+            .unwrap()
+            .entry(name.clone())
+            .or_insert_with(|| {
+                let mut builder = self.meter.f64_histogram(name.clone());
+                if let Some((unit, description)) = self.description(&name) {
+                    builder = builder.with_description(description.into_owned());
+                    if let Some(unit) = unit {
+                        builder = builder.with_unit(unit.as_canonical_label());
+                    }
+                }
+                builder.build()
+            })
+            .clone();
+
+        Histogram::from_arc(Arc::new(OtelHistogram {
+            histogram,
+            attributes: attributes(key),
+        }))
+    }
+}
+
+struct OtelCounter {
+    counter: opentelemetry::metrics::Counter<u64>,
+    attributes: Vec<KeyValue>,
+}
+
+impl CounterFn for OtelCounter {
+    fn increment(&self, value: u64) {
+        self.counter.add(value, &self.attributes);
+    }
+
+    fn absolute(&self, _value: u64) {
+        // OpenTelemetry counters only accept increments.
+    }
+}
+
+struct OtelGauge {
+    gauge: opentelemetry::metrics::Gauge<f64>,
+    value: Arc<AtomicU64>, // f64 bits, shared by every handle for the same key
+    attributes: Vec<KeyValue>,
+}
+
+impl OtelGauge {
+    fn update(&self, f: impl Fn(f64) -> f64) {
+        let previous = self
+            .value
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |bits| {
+                Some(f(f64::from_bits(bits)).to_bits())
+            })
+            .unwrap();
+        self.gauge
+            .record(f(f64::from_bits(previous)), &self.attributes);
+    }
+}
+
+impl GaugeFn for OtelGauge {
+    fn increment(&self, value: f64) {
+        self.update(|current| current + value);
+    }
+
+    fn decrement(&self, value: f64) {
+        self.update(|current| current - value);
+    }
+
+    fn set(&self, value: f64) {
+        self.update(|_| value);
+    }
+}
+
+struct OtelHistogram {
+    histogram: opentelemetry::metrics::Histogram<f64>,
+    attributes: Vec<KeyValue>,
+}
+
+impl HistogramFn for OtelHistogram {
+    fn record(&self, value: f64) {
+        self.histogram.record(value, &self.attributes);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::telemetry::{describe_metrics, REQUESTS_TOTAL, REQUEST_DURATION_SECONDS};
+    use opentelemetry::metrics::MeterProvider;
+    use opentelemetry_sdk::metrics::{InMemoryMetricExporter, PeriodicReader, SdkMeterProvider};
+
+    #[test]
+    fn test_metrics_are_forwarded_to_meter() {
+        let exporter = InMemoryMetricExporter::default();
+        let provider = SdkMeterProvider::builder()
+            .with_reader(PeriodicReader::builder(exporter.clone()).build())
+            .build();
+        let recorder = OpenTelemetryRecorder::new(provider.meter("bitvora"));
+
+        metrics::with_local_recorder(&recorder, || {
+            describe_metrics();
+            metrics::counter!(REQUESTS_TOTAL, "operation" => "get_balance").increment(2);
+            metrics::histogram!(REQUEST_DURATION_SECONDS, "operation" => "get_balance")
+                .record(0.25);
+        });
+        provider.force_flush().unwrap();
+
+        let exported = exporter.get_finished_metrics().unwrap();
+        let names: Vec<String> = exported
+            .iter()
+            .flat_map(|resource| resource.scope_metrics())
+            .flat_map(|scope| scope.metrics())
+            .map(|metric| metric.name().to_string())
+            .collect();
+        assert!(names.contains(&REQUESTS_TOTAL.to_string()));
+        assert!(names.contains(&REQUEST_DURATION_SECONDS.to_string()));
+    }
+}
@@ -0,0 +1,255 @@
+//! Stub transport and response fixtures shared by the unit tests.
+
+use crate::BitvoraClient;
+use reqwest::{Method, Url};
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tower::util::BoxCloneSyncService;
+use tower::{service_fn, BoxError};
+
+pub(crate) const BASE_URL: &str = "https://api.example.com";
+
+pub(crate) type Transport = BoxCloneSyncService<reqwest::Request, reqwest::Response, BoxError>;
+
+/// A request as the stub transport received it.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Recorded {
+    pub(crate) method: Method,
+    pub(crate) url: Url,
+    pub(crate) body: Option<String>,
+    pub(crate) authorization: Option<String>,
+}
+
+impl Recorded {
+    pub(crate) fn path(&self) -> &str {
+        self.url.path()
+    }
+
+    /// The path, followed by `?` and the query string if there is one.
+    pub(crate) fn target(&self) -> String {
+        match self.url.query() {
+            Some(query) => format!("{}?{}", self.url.path(), query),
+            None => self.url.path().to_string(),
+        }
+    }
+
+    /// The decoded value of query parameter `key`.
+    pub(crate) fn param(&self, key: &str) -> Option<String> {
+        self.url
+            .query_pairs()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value.into_owned())
+    }
+}
+
+/// What the stub transport answers with.
+#[derive(Debug, Clone)]
+pub(crate) struct Reply {
+    status: u16,
+    body: String,
+    delay: Duration,
+}
+
+impl Reply {
+    /// A 200 response wrapping `data` the way the API does.
+    pub(crate) fn ok(data: Value) -> Self {
+        Self::json(json!({"status": 200, "message": "ok", "data": data}))
+    }
+
+    pub(crate) fn json(body: Value) -> Self {
+        Self::status(200, body.to_string())
+    }
+
+    pub(crate) fn status(status: u16, body: impl Into<String>) -> Self {
+        Self {
+            status,
+            body: body.into(),
+            delay: Duration::ZERO,
+        }
+    }
+
+    /// Answers only after `delay`.
+    pub(crate) fn after(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+}
+
+/// Every request the stub transport has received. Clones share the log.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Requests {
+    seen: Arc<Mutex<Vec<Recorded>>>,
+    in_flight: Arc<AtomicUsize>,
+    peak: Arc<AtomicUsize>,
+}
+
+impl Requests {
+    pub(crate) fn all(&self) -> Vec<Recorded> {
+        self.seen.lock().unwrap().clone()
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.seen.lock().unwrap().len()
+    }
+
+    pub(crate) fn targets(&self) -> Vec<String> {
+        self.all().iter().map(Recorded::target).collect()
+    }
+
+    pub(crate) fn bodies(&self) -> Vec<String> {
+        self.all()
+            .into_iter()
+            .filter_map(|request| request.body)
+            .collect()
+    }
+
+    /// Most requests that were awaiting their reply at the same time.
+    pub(crate) fn peak_in_flight(&self) -> usize {
+        self.peak.load(Ordering::SeqCst)
+    }
+}
+
+/// A transport that records each request and answers it with `respond`.
+pub(crate) fn transport(
+    respond: impl Fn(&Recorded) -> Reply + Send + Sync + 'static,
+) -> (Transport, Requests) {
+    let requests = Requests::default();
+    let log = requests.clone();
+    let respond = Arc::new(respond);
+    let service = service_fn(move |request: reqwest::Request| {
+        let recorded = Recorded {
+            method: request.method().clone(),
+            url: request.url().clone(),
+            body: request
+                .body()
+                .and_then(|body| body.as_bytes())
+                .map(|body| String::from_utf8(body.to_vec()).unwrap()),
+            authorization: request
+                .headers()
+                .get("authorization")
+                .map(|value| value.to_str().unwrap().to_string()),
+        };
+        let reply = respond(&recorded);
+        log.seen.lock().unwrap().push(recorded);
+        let current = log.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+        log.peak.fetch_max(current, Ordering::SeqCst);
+        let in_flight = log.in_flight.clone();
+        async move {
+            if !reply.delay.is_zero() {
+                tokio::time::sleep(reply.delay).await;
+            }
+            in_flight.fetch_sub(1, Ordering::SeqCst);
+            Ok::<_, BoxError>(reqwest::Response::from(
+                http::Response::builder()
+                    .status(reply.status)
+                    .body(reply.body)
+                    .unwrap(),
+            ))
+        }
+    });
+    (BoxCloneSyncService::new(service), requests)
+}
+
+/// A client for [`BASE_URL`] with key `secret` whose requests go to a stub
+/// [`transport`].
+pub(crate) fn client(
+    respond: impl Fn(&Recorded) -> Reply + Send + Sync + 'static,
+) -> (BitvoraClient, Requests) {
+    let (transport, requests) = transport(respond);
+    let client = BitvoraClient::with_transport(BASE_URL, "secret", transport);
+    (client, requests)
+}
+
+/// A ledger row as listed by `/v1/transactions`.
+pub(crate) fn transaction(id: &str, kind: &str, status: &str) -> Value {
+    json!({
+        "id": id,
+        "company_id": "c",
+        "amount_sats": 21,
+        "recipient": "r",
+        "rail_type": "lightning",
+        "type": kind,
+        "fee_microsats": 0,
+        "status": status,
+        "created_at": "2024-01-01T00:00:00Z",
+    })
+}
+
+pub(crate) fn deposit(id: &str, status: &str) -> Value {
+    json!({
+        "id": id,
+        "ledger_tx_id": "l",
+        "recipient": "r",
+        "amount_sats": 21,
+        "fee_sats": 0.0,
+        "chain_tx_id": null,
+        "rail_type": "lightning",
+        "network_type": "signet",
+        "status": status,
+        "metadata": null,
+        "lightning_invoice_id": null,
+        "created_at": "2024-01-01T00:00:00Z",
+    })
+}
+
+pub(crate) fn withdrawal(id: &str, status: &str) -> Value {
+    json!({
+        "id": id,
+        "amount_sats": 21,
+        "recipient": "r",
+        "fee_sats": 0.0,
+        "network_type": "signet",
+        "rail_type": "lightning",
+        "status": status,
+        "lightning_payment": null,
+        "chain_tx_id": null,
+        "metadata": null,
+        "created_at": "2024-01-01T00:00:00Z",
+    })
+}
+
+/// A settled lightning payment as found on a withdrawal.
+pub(crate) fn lightning_payment(preimage: &str) -> Value {
+    json!({
+        "payment_hash": "h",
+        "value": "21",
+        "creation_date": "0",
+        "fee": "0",
+        "payment_preimage": preimage,
+        "value_sat": "21",
+        "value_msat": "21000",
+        "payment_request": "lnbc",
+        "status": "SUCCEEDED",
+        "fee_sat": "0",
+        "fee_msat": "0",
+        "creation_time_ns": "0",
+        "htlcs": [],
+        "payment_index": "1",
+        "failure_reason": "",
+    })
+}
+
+pub(crate) fn lightning_invoice(id: &str, settled: bool) -> Value {
+    json!({
+        "id": id,
+        "node_id": "n",
+        "memo": "m",
+        "r_preimage": "p",
+        "r_hash": "h",
+        "amount_sats": 21,
+        "settled": settled,
+        "payment_request": "lnbc",
+        "metadata": null,
+        "lightning_address_id": null,
+    })
+}
+
+/// Sets `fields` on a JSON object fixture.
+pub(crate) fn with(mut value: Value, fields: Value) -> Value {
+    for (key, field) in fields.as_object().unwrap() {
+        value[key] = field.clone();
+    }
+    value
+}
@@ -0,0 +1,304 @@
+//! `tracing` instrumentation for API calls, enabled with the `tracing` feature.
+//!
+//! Every endpoint call runs inside a `bitvora.request` span carrying the
+//! operation, HTTP method and path, status, latency, retry count and the ids of
+//! the withdrawal, deposit or invoice involved. The API key and response bodies
+//! are never recorded, and destinations are only recorded through [`redact`].
+//!
+//! The client can't see retries made by tower layers on its own, so
+//! `retry_count` is only recorded when the transport includes
+//! [`CountAttemptsLayer`]; without it the field stays empty.
+
+use crate::errors::APIError;
+use crate::service::Operation;
+use sha2::{Digest, Sha256};
+use std::cell::Cell;
+use std::future::Future;
+use std::task::{Context, Poll};
+use std::time::Instant;
+use tower::{Layer, Service};
+use tracing::field::Empty;
+use tracing::{Instrument, Span};
+
+tokio::task_local! {
+    static ATTEMPTS: Cell<u32>;
+}
+
+/// Hashes a sensitive value so it can be correlated across log lines without
+/// being disclosed.
+pub fn redact(value: &str) -> String {
+    let digest = Sha256::digest(value.as_bytes());
+    format!("sha256:{}", hex::encode(&digest[..8]))
+}
+
+pub(crate) fn request_span<O: Operation>(operation: &O) -> Span {
+    let span = tracing::info_span!(
+        "bitvora.request",
+        operation = O::NAME,
+        http.method = %operation.method(),
+        http.path = %operation.path(),
+        http.status = Empty,
+        latency_ms = Empty,
+        // Only filled in when the transport has a `CountAttemptsLayer`.
+        retry_count = Empty,
+        withdrawal_id = Empty,
+        deposit_id = Empty,
+        invoice_id = Empty,
+        destination = Empty,
+        error = Empty,
+    );
+    operation.record(&span);
+    span
+}
+
+/// Runs a single API call inside `span`, recording its outcome.
+pub(crate) async fn instrument<O, F>(span: Span, call: F) -> Result<O::Response, APIError>
+where
+    O: Operation,
+    F: Future<Output = Result<O::Response, APIError>>,
+{
+    let start = Instant::now();
+    let (result, attempts) = ATTEMPTS
+        .scope(Cell::new(0), async {
+            let result = call.await;
+            (result, ATTEMPTS.with(Cell::get))
+        })
+        .instrument(span.clone())
+        .await;
+
+    span.record("latency_ms", start.elapsed().as_millis() as u64);
+    // No attempts counted means no `CountAttemptsLayer`, not zero retries.
+    if attempts > 0 {
+        span.record("retry_count", attempts - 1);
+    }
+
+    match &result {
+        Ok(response) => O::record_response(response, &span),
+        Err(err) => {
+            span.record("error", err.kind());
+            span.in_scope(|| tracing::warn!(error = err.kind(), "bitvora request failed"));
+        }
+    }
+
+    result
+}
+
+/// Counts the attempts made for each API call so its span can report a retry
+/// count. Without it, spans have no `retry_count`.
+///
+/// Place it below any retry layer, directly around the innermost transport:
+///
+/// ```ignore
+/// ServiceBuilder::new()
+///     .retry(policy)
+///     .layer(CountAttemptsLayer)
+///     .service(reqwest::Client::new())
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CountAttemptsLayer;
+
+impl<S> Layer<S> for CountAttemptsLayer {
+    type Service = CountAttempts<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        CountAttempts { inner }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct CountAttempts<S> {
+    inner: S,
+}
+
+impl<S, R> Service<R> for CountAttempts<S>
+where
+    S: Service<R>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: R) -> Self::Future {
+        // Outside of a client call (e.g. behind a `Buffer` worker) there is
+        // nothing to count.
+        let _ = ATTEMPTS.try_with(|attempts| {
+            attempts.set(attempts.get() + 1);
+            tracing::debug!(attempt = attempts.get(), "sending bitvora request");
+        });
+        self.inner.call(request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::WithdrawRequest;
+    use crate::test_support::{self, with, Reply};
+    use crate::BitvoraClient;
+    use serde_json::json;
+    use std::collections::HashMap;
+    use std::fmt;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::{Arc, Mutex};
+    use tower::{service_fn, BoxError, ServiceBuilder, ServiceExt};
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Subscriber};
+    use tracing_subscriber::layer::{Context as LayerContext, SubscriberExt};
+
+    #[test]
+    fn test_redact_is_stable_and_hides_value() {
+        let redacted = redact("sillyzebu667@signet.bitvora.me");
+        assert_eq!(redacted, redact("sillyzebu667@signet.bitvora.me"));
+        assert!(redacted.starts_with("sha256:"));
+        assert!(!redacted.contains("sillyzebu"));
+    }
+
+    #[tokio::test]
+    async fn test_attempts_are_counted_per_call() {
+        let calls = Arc::new(AtomicU32::new(0));
+        let counted = calls.clone();
+        let mut transport = ServiceBuilder::new()
+            .layer(CountAttemptsLayer)
+            .service(service_fn(move |_request: ()| {
+                counted.fetch_add(1, Ordering::SeqCst);
+                async move { Ok::<_, BoxError>(()) }
+            }));
+
+        let attempts = ATTEMPTS
+            .scope(Cell::new(0), async {
+                transport.ready().await.unwrap().call(()).await.unwrap();
+                transport.ready().await.unwrap().call(()).await.unwrap();
+                ATTEMPTS.with(Cell::get)
+            })
+            .await;
+
+        assert_eq!(attempts, 2);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    /// Collects the fields of every span and event as `(name, value)`.
+    #[derive(Clone, Default)]
+    struct Capture {
+        fields: Arc<Mutex<Vec<(String, String)>>>,
+    }
+
+    impl Capture {
+        fn value(&self, name: &str) -> Option<String> {
+            let fields = self.fields.lock().unwrap();
+            fields
+                .iter()
+                .rev()
+                .find(|(field, _)| field == name)
+                .map(|(_, value)| value.clone())
+        }
+    }
+
+    impl Visit for Capture {
+        fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+            self.fields
+                .lock()
+                .unwrap()
+                .push((field.name().to_string(), format!("{:?}", value)));
+        }
+
+        fn record_str(&mut self, field: &Field, value: &str) {
+            self.fields
+                .lock()
+                .unwrap()
+                .push((field.name().to_string(), value.to_string()));
+        }
+    }
+
+    impl<S: Subscriber> tracing_subscriber::Layer<S> for Capture {
+        fn on_new_span(&self, attrs: &Attributes<'_>, _id: &Id, _ctx: LayerContext<'_, S>) {
+            attrs.record(&mut self.clone());
+        }
+
+        fn on_record(&self, _span: &Id, values: &Record<'_>, _ctx: LayerContext<'_, S>) {
+            values.record(&mut self.clone());
+        }
+
+        fn on_event(&self, event: &Event<'_>, _ctx: LayerContext<'_, S>) {
+            event.record(&mut self.clone());
+        }
+    }
+
+    /// Captures everything traced until the guard is dropped.
+    fn capture() -> (Capture, tracing::subscriber::DefaultGuard) {
+        let capture = Capture::default();
+        let subscriber = tracing_subscriber::registry().with(capture.clone());
+        (capture, tracing::subscriber::set_default(subscriber))
+    }
+
+    #[tokio::test]
+    async fn test_request_span_fields_and_redaction() {
+        let (capture, _guard) = capture();
+        let (stub, _) = test_support::transport(|_| {
+            Reply::ok(with(
+                test_support::withdrawal("w1", "completed"),
+                json!({"lightning_payment": test_support::lightning_payment("preimage-beef")}),
+            ))
+        });
+        // Fails the first attempt, the way a retry layer would see it.
+        let counted = CountAttemptsLayer.layer(stub);
+        let transport = service_fn(move |request: reqwest::Request| {
+            let mut counted = counted.clone();
+            async move {
+                let retry = request.try_clone().unwrap();
+                counted.ready().await?.call(request).await?;
+                counted.ready().await?.call(retry).await
+            }
+        });
+        let client = BitvoraClient::with_transport(test_support::BASE_URL, "sk-secret", transport);
+
+        client
+            .withdraw(WithdrawRequest {
+                amount: 21.0,
+                currency: "sats".to_string(),
+                destination: "alice@example.com".to_string(),
+                metadata: HashMap::new(),
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(capture.value("operation").as_deref(), Some("withdraw"));
+        assert_eq!(capture.value("http.status").as_deref(), Some("200"));
+        assert_eq!(capture.value("retry_count").as_deref(), Some("1"));
+        assert_eq!(capture.value("withdrawal_id").as_deref(), Some("w1"));
+        assert_eq!(
+            capture.value("destination"),
+            Some(redact("alice@example.com"))
+        );
+        for (field, value) in capture.fields.lock().unwrap().iter() {
+            for secret in ["sk-secret", "preimage-beef", "alice@example.com"] {
+                assert!(!value.contains(secret), "{field} records {secret}");
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retry_count_needs_count_attempts_layer() {
+        let (capture, _guard) = capture();
+        let (client, _) = test_support::client(|_| Reply::ok(json!({"balance": 1})));
+
+        client.get_balance().await.unwrap();
+
+        assert_eq!(capture.value("operation").as_deref(), Some("get_balance"));
+        assert_eq!(capture.value("retry_count"), None);
+    }
+
+    #[tokio::test]
+    async fn test_counting_is_a_no_op_outside_client_calls() {
+        let mut transport =
+            CountAttemptsLayer.layer(service_fn(
+                |_request: ()| async move { Ok::<_, BoxError>(()) },
+            ));
+
+        transport.ready().await.unwrap().call(()).await.unwrap();
+    }
+}
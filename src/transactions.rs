@@ -0,0 +1,178 @@
+//! Walking the full transaction history page by page.
+//!
+//! ```ignore
+//! let mut transactions = Box::pin(client.transactions_stream(TransactionQuery {
+//!     limit: Some(500),
+//!     ..Default::default()
+//! }));
+//! while let Some(transaction) = transactions.next().await {
+//!     export(transaction?)?;
+//! }
+//! ```
+//!
+//! Pages are only fetched as the stream is consumed, so memory use stays at
+//! one page plus whatever is prefetched. To resume an interrupted export,
+//! consume [`TransactionStream::pages`] instead and save each page's
+//! [`TransactionPage::next`] once it has been processed.
+
+use crate::client::BitvoraClient;
+use crate::errors::APIError;
+use crate::models::{Pagination, Transaction, TransactionQuery};
+use futures_util::{stream, Stream, StreamExt, TryStreamExt};
+use tokio::sync::mpsc;
+
+/// One page of transactions and where to continue from.
+#[derive(Debug)]
+pub struct TransactionPage {
+    pub transactions: Vec<Transaction>,
+    pub pagination: Pagination,
+    /// Query for the following page, or `None` if this is the last one. Pass
+    /// it to [`TransactionStream::new`] to resume after this page.
+    pub next: Option<TransactionQuery>,
+}
+
+/// Lazily pages through every transaction matching a query.
+#[derive(Debug)]
+pub struct TransactionStream {
+    client: BitvoraClient,
+    query: TransactionQuery,
+    prefetch: usize,
+}
+
+impl TransactionStream {
+    /// Starts at the page `query` points to, so a query with a saved cursor
+    /// or offset resumes from there.
+    pub fn new(client: BitvoraClient, query: TransactionQuery) -> Self {
+        Self {
+            client,
+            query,
+            prefetch: 0,
+        }
+    }
+
+    /// Fetches up to `pages` pages ahead of the consumer in a background
+    /// task. Defaults to 0, fetching each page only when it is needed.
+    pub fn with_prefetch(mut self, pages: usize) -> Self {
+        self.prefetch = pages;
+        self
+    }
+
+    /// Yields each page in turn. The stream ends after the last page, or
+    /// after the first error.
+    pub fn pages(self) -> impl Stream<Item = Result<TransactionPage, APIError>> + Send {
+        let pages = fetch_pages(self.client, self.query);
+        if self.prefetch == 0 {
+            return pages.left_stream();
+        }
+
+        let (sender, receiver) = mpsc::channel(self.prefetch);
+        tokio::spawn(async move {
+            let mut pages = Box::pin(pages);
+            while let Some(page) = pages.next().await {
+                // The consumer dropped the stream; stop fetching.
+                if sender.send(page).await.is_err() {
+                    break;
+                }
+            }
+        });
+        stream::unfold(receiver, |mut receiver| async move {
+            let page = receiver.recv().await?;
+            Some((page, receiver))
+        })
+        .right_stream()
+    }
+
+    /// Yields every transaction across all pages.
+    pub fn into_stream(self) -> impl Stream<Item = Result<Transaction, APIError>> + Send {
+        self.pages()
+            .map_ok(|page| stream::iter(page.transactions.into_iter().map(Ok)))
+            .try_flatten()
+    }
+}
+
+fn fetch_pages(
+    client: BitvoraClient,
+    query: TransactionQuery,
+) -> impl Stream<Item = Result<TransactionPage, APIError>> + Send {
+    stream::unfold(Some(query), move |query| {
+        let client = client.clone();
+        async move {
+            let query = query?;
+            match client.list_transactions(query.clone()).await {
+                Ok(response) => {
+                    let next = response.next_query(&query);
+                    let page = TransactionPage {
+                        transactions: response.data,
+                        pagination: response.pagination,
+                        next: next.clone(),
+                    };
+                    Some((Ok(page), next))
+                }
+                Err(err) => Some((Err(err), None)),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{self, transaction, Reply, Requests};
+    use serde_json::json;
+
+    /// Serves transactions `t0`..`t6` two per page, paged by cursor.
+    fn client() -> (BitvoraClient, Requests) {
+        test_support::client(|request| {
+            let start: usize = request.param("cursor").map_or(0, |c| c.parse().unwrap());
+            let end = (start + 2).min(7);
+            let rows: Vec<_> = (start..end)
+                .map(|i| transaction(&format!("t{i}"), "deposit", "completed"))
+                .collect();
+            Reply::json(json!({
+                "status": 200,
+                "message": "ok",
+                "data": rows,
+                "pagination": {"has_more": end < 7, "next_cursor": end.to_string()},
+            }))
+        })
+    }
+
+    fn ids(transactions: Vec<Transaction>) -> Vec<String> {
+        transactions.into_iter().map(|tx| tx.id).collect()
+    }
+
+    #[tokio::test]
+    async fn test_streams_every_page_lazily() {
+        let (client, requests) = client();
+        let mut transactions = Box::pin(client.transactions_stream(TransactionQuery::default()));
+
+        assert_eq!(transactions.next().await.unwrap().unwrap().id, "t0");
+        assert_eq!(requests.len(), 1);
+
+        let rest: Vec<_> = transactions.try_collect().await.unwrap();
+        assert_eq!(ids(rest), ["t1", "t2", "t3", "t4", "t5", "t6"]);
+        assert_eq!(requests.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_prefetch_and_resume_from_saved_page() {
+        let (client, _) = client();
+        let mut pages = Box::pin(
+            TransactionStream::new(client.clone(), TransactionQuery::default())
+                .with_prefetch(2)
+                .pages(),
+        );
+        let first = pages.next().await.unwrap().unwrap();
+        drop(pages);
+
+        let resumed: Vec<_> = TransactionStream::new(client, first.next.unwrap())
+            .with_prefetch(2)
+            .into_stream()
+            .try_collect()
+            .await
+            .unwrap();
+
+        assert_eq!(ids(first.transactions), ["t0", "t1"]);
+        assert_eq!(ids(resumed), ["t2", "t3", "t4", "t5", "t6"]);
+    }
+}
@@ -0,0 +1,95 @@
+//! Shared polling loop behind the `wait_*` methods of the object handles.
+
+use crate::errors::{APIError, WaitError};
+use crate::webhooks::WebhookEvent;
+use std::future::Future;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::time::Instant;
+
+pub(crate) const DEFAULT_INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+pub(crate) const DEFAULT_MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Exponential backoff between polls.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Backoff {
+    pub(crate) initial: Duration,
+    pub(crate) max: Duration,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            initial: DEFAULT_INITIAL_BACKOFF,
+            max: DEFAULT_MAX_BACKOFF,
+        }
+    }
+}
+
+/// What a webhook event means for the object being waited on.
+pub(crate) enum Woken<T> {
+    /// The event carries the object's new state.
+    State(T),
+    /// The event concerns the object; poll again right away.
+    Recheck,
+}
+
+/// Polls `fetch` with backoff until `is_done` holds or `timeout` elapses.
+///
+/// Between polls it also listens on `events`, if given, and finishes as soon
+/// as `from_event` turns one into a state that is done.
+pub(crate) async fn wait_for<T, F, Fut>(
+    timeout: Duration,
+    backoff: Backoff,
+    mut fetch: F,
+    is_done: impl Fn(&T) -> bool,
+    mut events: Option<&mut broadcast::Receiver<WebhookEvent>>,
+    from_event: impl Fn(WebhookEvent) -> Option<Woken<T>>,
+) -> Result<T, WaitError<T>>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, APIError>>,
+{
+    // Timeouts too large to represent mean waiting without a deadline.
+    let deadline = Instant::now().checked_add(timeout);
+    let mut delay = backoff.initial;
+
+    loop {
+        let current = fetch().await?;
+        if is_done(&current) {
+            return Ok(current);
+        }
+
+        let now = Instant::now();
+        let remaining = deadline.map_or(delay, |deadline| deadline.saturating_duration_since(now));
+        if remaining.is_zero() {
+            return Err(WaitError::Timeout(current));
+        }
+        let sleep = tokio::time::sleep(delay.min(remaining));
+        tokio::pin!(sleep);
+        delay = (delay * 2).min(backoff.max);
+
+        loop {
+            let receiver = match events.as_deref_mut() {
+                Some(receiver) => receiver,
+                None => {
+                    (&mut sleep).await;
+                    break;
+                }
+            };
+            tokio::select! {
+                _ = &mut sleep => break,
+                received = receiver.recv() => match received {
+                    Ok(event) => match from_event(event) {
+                        Some(Woken::State(state)) if is_done(&state) => return Ok(state),
+                        Some(Woken::Recheck) => break,
+                        _ => {}
+                    },
+                    Err(broadcast::error::RecvError::Lagged(_)) => {}
+                    // Nobody publishes events any more; rely on polling.
+                    Err(broadcast::error::RecvError::Closed) => events = None,
+                },
+            }
+        }
+    }
+}
@@ -0,0 +1,534 @@
+//! Webhook signature verification and typed webhook events.
+//!
+//! Bitvora signs every webhook delivery with the endpoint's secret: the
+//! [`SIGNATURE_HEADER`] carries the hex HMAC-SHA256 of `"{timestamp}.{body}"`,
+//! where the timestamp (Unix seconds) is sent in [`TIMESTAMP_HEADER`].
+//!
+//! ```ignore
+//! let verifier = WebhookVerifier::new(webhook_secret).with_replay_protection();
+//! let event = verifier.verify_headers(&headers, &body)?;
+//! match event {
+//!     WebhookEvent::DepositLightningCompleted(deposit) => { /* ... */ }
+//!     _ => {}
+//! }
+//! ```
+
+use crate::models::{GetDepositData, WithdrawData};
+use hmac::{Hmac, Mac};
+use http::StatusCode;
+use serde::Deserialize;
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use zeroize::Zeroizing;
+
+pub const SIGNATURE_HEADER: &str = "bitvora-signature";
+pub const TIMESTAMP_HEADER: &str = "bitvora-timestamp";
+
+/// How far a delivery's timestamp may be from the local clock by default.
+pub const DEFAULT_TOLERANCE: Duration = Duration::from_secs(300);
+
+type HmacSha256 = Hmac<Sha256>;
+
+#[cfg(feature = "actix-web")]
+pub mod actix;
+#[cfg(feature = "axum")]
+pub mod axum;
+#[cfg(feature = "hyper")]
+pub mod hyper;
+pub mod simulator;
+pub mod store;
+
+/// Every event type Bitvora currently sends.
+pub const EVENT_TYPES: &[&str] = &[
+    "deposit.lightning.completed",
+    "deposit.lightning.failed",
+    "deposit.onchain.pending",
+    "deposit.onchain.completed",
+    "withdrawal.pending",
+    "withdrawal.completed",
+    "withdrawal.failed",
+];
+
+#[derive(Debug, Clone)]
+pub enum WebhookEvent {
+    DepositLightningCompleted(GetDepositData),
+    DepositLightningFailed(GetDepositData),
+    DepositOnChainPending(GetDepositData),
+    DepositOnChainCompleted(GetDepositData),
+    WithdrawalPending(WithdrawData),
+    WithdrawalCompleted(WithdrawData),
+    WithdrawalFailed(WithdrawData),
+    /// An event type this version of the SDK doesn't know about.
+    Unknown {
+        event: String,
+        data: serde_json::Value,
+    },
+}
+
+#[derive(Deserialize)]
+struct Envelope {
+    event: String,
+    data: serde_json::Value,
+}
+
+impl WebhookEvent {
+    /// Parses a webhook body without verifying its signature.
+    pub fn from_slice(body: &[u8]) -> Result<Self, serde_json::Error> {
+        let Envelope { event, data } = serde_json::from_slice(body)?;
+
+        let parsed = match event.as_str() {
+            "deposit.lightning.completed" => {
+                WebhookEvent::DepositLightningCompleted(serde_json::from_value(data)?)
+            }
+            "deposit.lightning.failed" => {
+                WebhookEvent::DepositLightningFailed(serde_json::from_value(data)?)
+            }
+            "deposit.onchain.pending" => {
+                WebhookEvent::DepositOnChainPending(serde_json::from_value(data)?)
+            }
+            "deposit.onchain.completed" => {
+                WebhookEvent::DepositOnChainCompleted(serde_json::from_value(data)?)
+            }
+            "withdrawal.pending" => WebhookEvent::WithdrawalPending(serde_json::from_value(data)?),
+            "withdrawal.completed" => {
+                WebhookEvent::WithdrawalCompleted(serde_json::from_value(data)?)
+            }
+            "withdrawal.failed" => WebhookEvent::WithdrawalFailed(serde_json::from_value(data)?),
+            _ => WebhookEvent::Unknown { event, data },
+        };
+        Ok(parsed)
+    }
+
+    /// The event type as sent by Bitvora, e.g. `"withdrawal.completed"`.
+    pub fn event_type(&self) -> &str {
+        match self {
+            WebhookEvent::DepositLightningCompleted(_) => "deposit.lightning.completed",
+            WebhookEvent::DepositLightningFailed(_) => "deposit.lightning.failed",
+            WebhookEvent::DepositOnChainPending(_) => "deposit.onchain.pending",
+            WebhookEvent::DepositOnChainCompleted(_) => "deposit.onchain.completed",
+            WebhookEvent::WithdrawalPending(_) => "withdrawal.pending",
+            WebhookEvent::WithdrawalCompleted(_) => "withdrawal.completed",
+            WebhookEvent::WithdrawalFailed(_) => "withdrawal.failed",
+            WebhookEvent::Unknown { event, .. } => event,
+        }
+    }
+
+    /// Id of the deposit or withdrawal the event is about.
+    pub fn object_id(&self) -> Option<&str> {
+        match self {
+            WebhookEvent::DepositLightningCompleted(deposit)
+            | WebhookEvent::DepositLightningFailed(deposit)
+            | WebhookEvent::DepositOnChainPending(deposit)
+            | WebhookEvent::DepositOnChainCompleted(deposit) => Some(&deposit.id),
+            WebhookEvent::WithdrawalPending(withdrawal)
+            | WebhookEvent::WithdrawalCompleted(withdrawal)
+            | WebhookEvent::WithdrawalFailed(withdrawal) => Some(&withdrawal.id),
+            WebhookEvent::Unknown { data, .. } => data.get("id").and_then(|id| id.as_str()),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum WebhookError {
+    MissingHeader(&'static str),
+    InvalidTimestamp,
+    TimestampOutOfTolerance,
+    InvalidSignature,
+    Replayed,
+    Payload(serde_json::Error),
+}
+
+impl fmt::Display for WebhookError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WebhookError::MissingHeader(name) => write!(f, "Missing webhook header: {}", name),
+            WebhookError::InvalidTimestamp => write!(f, "Invalid webhook timestamp"),
+            WebhookError::TimestampOutOfTolerance => {
+                write!(f, "Webhook timestamp is outside the tolerance window")
+            }
+            WebhookError::InvalidSignature => write!(f, "Invalid webhook signature"),
+            WebhookError::Replayed => write!(f, "Webhook delivery was already received"),
+            WebhookError::Payload(err) => write!(f, "Invalid webhook payload: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for WebhookError {}
+
+impl WebhookError {
+    /// The status to answer the delivery with. Bitvora retries deliveries that
+    /// don't get a 2xx, so a replayed delivery is acknowledged while forged or
+    /// malformed ones are refused.
+    pub fn status(&self) -> StatusCode {
+        match self {
+            WebhookError::Replayed => StatusCode::OK,
+            WebhookError::Payload(_) => StatusCode::BAD_REQUEST,
+            WebhookError::MissingHeader(_)
+            | WebhookError::InvalidTimestamp
+            | WebhookError::TimestampOutOfTolerance
+            | WebhookError::InvalidSignature => StatusCode::UNAUTHORIZED,
+        }
+    }
+}
+
+impl From<serde_json::Error> for WebhookError {
+    fn from(err: serde_json::Error) -> Self {
+        WebhookError::Payload(err)
+    }
+}
+
+/// Computes the hex signature Bitvora sends for `body` at `timestamp`.
+pub fn sign(secret: &[u8], timestamp: u64, body: &[u8]) -> String {
+    let mut mac = HmacSha256::new_from_slice(secret).expect("HMAC accepts keys of any length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    hex::encode(mac.finalize().into_bytes())
+}
+
+pub struct WebhookVerifier {
+    secret: Zeroizing<Vec<u8>>,
+    tolerance: Duration,
+    // Signatures seen within the tolerance window, with their timestamps.
+    seen: Option<Mutex<HashMap<String, u64>>>,
+}
+
+impl WebhookVerifier {
+    pub fn new(secret: impl AsRef<[u8]>) -> Self {
+        Self {
+            secret: Zeroizing::new(secret.as_ref().to_vec()),
+            tolerance: DEFAULT_TOLERANCE,
+            seen: None,
+        }
+    }
+
+    /// Maximum allowed difference between a delivery's timestamp and the local
+    /// clock, in either direction.
+    pub fn with_tolerance(mut self, tolerance: Duration) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Rejects a signed delivery seen before within the tolerance window.
+    /// Older replays are already rejected by the timestamp check.
+    pub fn with_replay_protection(mut self) -> Self {
+        self.seen = Some(Mutex::new(HashMap::new()));
+        self
+    }
+
+    /// Verifies a delivery using the Bitvora signature and timestamp headers,
+    /// then parses its event.
+    pub fn verify_headers(
+        &self,
+        headers: &http::HeaderMap,
+        body: &[u8],
+    ) -> Result<WebhookEvent, WebhookError> {
+        let header = |name: &'static str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .ok_or(WebhookError::MissingHeader(name))
+        };
+        self.verify(header(SIGNATURE_HEADER)?, header(TIMESTAMP_HEADER)?, body)
+    }
+
+    /// Verifies a delivery's signature and timestamp, then parses its event.
+    pub fn verify(
+        &self,
+        signature: &str,
+        timestamp: &str,
+        body: &[u8],
+    ) -> Result<WebhookEvent, WebhookError> {
+        self.verify_at(signature, timestamp, body, SystemTime::now())
+    }
+
+    pub(crate) fn verify_at(
+        &self,
+        signature: &str,
+        timestamp: &str,
+        body: &[u8],
+        now: SystemTime,
+    ) -> Result<WebhookEvent, WebhookError> {
+        let timestamp: u64 = timestamp
+            .trim()
+            .parse()
+            .map_err(|_| WebhookError::InvalidTimestamp)?;
+        let now = now.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        if now.abs_diff(timestamp) > self.tolerance.as_secs() {
+            return Err(WebhookError::TimestampOutOfTolerance);
+        }
+
+        let expected = decode_signature(signature)?;
+        let mut mac =
+            HmacSha256::new_from_slice(&self.secret).expect("HMAC accepts keys of any length");
+        mac.update(timestamp.to_string().as_bytes());
+        mac.update(b".");
+        mac.update(body);
+        // Constant-time comparison.
+        mac.verify_slice(&expected)
+            .map_err(|_| WebhookError::InvalidSignature)?;
+
+        let event = WebhookEvent::from_slice(body)?;
+
+        if let Some(seen) = &self.seen {
+            let mut seen = seen.lock().unwrap();
+            let tolerance = self.tolerance.as_secs();
+            seen.retain(|_, seen_at| now.abs_diff(*seen_at) <= tolerance);
+            if seen.insert(hex::encode(&expected), timestamp).is_some() {
+                return Err(WebhookError::Replayed);
+            }
+        }
+
+        Ok(event)
+    }
+
+    /// Forgets a delivery seen by the replay protection, so that Bitvora's
+    /// redelivery of it is accepted again. Call it when handling a verified
+    /// event failed and the delivery is answered with an error.
+    pub fn forget(&self, signature: &str) {
+        let (Some(seen), Ok(signature)) = (&self.seen, decode_signature(signature)) else {
+            return;
+        };
+        seen.lock().unwrap().remove(&hex::encode(signature));
+    }
+}
+
+fn decode_signature(signature: &str) -> Result<Vec<u8>, WebhookError> {
+    let signature = signature.trim();
+    hex::decode(signature.strip_prefix("sha256=").unwrap_or(signature))
+        .map_err(|_| WebhookError::InvalidSignature)
+}
+
+impl fmt::Debug for WebhookVerifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WebhookVerifier")
+            .field("tolerance", &self.tolerance)
+            .field("replay_protection", &self.seen.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Application code run for each verified webhook event.
+///
+/// Implemented for async closures `Fn(WebhookEvent) -> Result<(), E>`. An
+/// error makes the receiver answer with a 500 so that Bitvora retries the
+/// delivery later.
+pub trait WebhookHandler: Clone + Send + Sync + 'static {
+    type Error: fmt::Display;
+    type Future: Future<Output = Result<(), Self::Error>> + Send;
+
+    fn handle(&self, event: WebhookEvent) -> Self::Future;
+}
+
+impl<F, Fut, E> WebhookHandler for F
+where
+    F: Fn(WebhookEvent) -> Fut + Clone + Send + Sync + 'static,
+    Fut: Future<Output = Result<(), E>> + Send,
+    E: fmt::Display,
+{
+    type Error = E;
+    type Future = Fut;
+
+    fn handle(&self, event: WebhookEvent) -> Self::Future {
+        self(event)
+    }
+}
+
+/// Verifies a delivery, hands its event to `handler` and returns the status to
+/// answer Bitvora with. The framework adapters are thin wrappers around this.
+///
+/// A delivery whose handler fails is forgotten by the verifier's replay
+/// protection, so that the redelivery is handled rather than acknowledged.
+pub async fn receive<H: WebhookHandler>(
+    verifier: &WebhookVerifier,
+    handler: &H,
+    headers: &http::HeaderMap,
+    body: &[u8],
+) -> StatusCode {
+    let event = match verifier.verify_headers(headers, body) {
+        Ok(event) => event,
+        Err(err) => return err.status(),
+    };
+
+    match handler.handle(event).await {
+        Ok(()) => StatusCode::OK,
+        Err(err) => {
+            #[cfg(feature = "tracing")]
+            tracing::error!(error = %err, "webhook handler failed");
+            #[cfg(not(feature = "tracing"))]
+            let _ = err;
+            if let Some(signature) = headers
+                .get(SIGNATURE_HEADER)
+                .and_then(|value| value.to_str().ok())
+            {
+                verifier.forget(signature);
+            }
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    pub(crate) const SECRET: &[u8] = b"whsec_test";
+    const NOW: u64 = 1_700_000_000;
+
+    pub(crate) fn withdrawal_body() -> Vec<u8> {
+        br#"{"event":"withdrawal.completed","data":{"id":"w1","amount_sats":21,"recipient":"someone@bitvora.me","fee_sats":0.0,"network_type":"signet","rail_type":"lightning","status":"settled","lightning_payment":null,"chain_tx_id":null,"metadata":null,"created_at":"2024-01-01T00:00:00Z"}}"#.to_vec()
+    }
+
+    fn at(secs: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn test_valid_signature_parses_event() {
+        let body = withdrawal_body();
+        let signature = sign(SECRET, NOW, &body);
+
+        let event = WebhookVerifier::new(SECRET)
+            .verify_at(&signature, &NOW.to_string(), &body, at(NOW + 10))
+            .unwrap();
+
+        match &event {
+            WebhookEvent::WithdrawalCompleted(withdrawal) => assert_eq!(withdrawal.id, "w1"),
+            other => panic!("unexpected event: {:?}", other),
+        }
+        assert_eq!(event.event_type(), "withdrawal.completed");
+        assert_eq!(event.object_id(), Some("w1"));
+    }
+
+    #[test]
+    fn test_tampered_body_is_rejected() {
+        let body = withdrawal_body();
+        let signature = sign(SECRET, NOW, &body);
+        let tampered = String::from_utf8(body).unwrap().replace("21", "2100");
+
+        let result = WebhookVerifier::new(SECRET).verify_at(
+            &signature,
+            &NOW.to_string(),
+            tampered.as_bytes(),
+            at(NOW),
+        );
+
+        assert!(matches!(result, Err(WebhookError::InvalidSignature)));
+    }
+
+    #[test]
+    fn test_stale_timestamp_is_rejected() {
+        let body = withdrawal_body();
+        let signature = sign(SECRET, NOW, &body);
+
+        let result = WebhookVerifier::new(SECRET)
+            .with_tolerance(Duration::from_secs(60))
+            .verify_at(&signature, &NOW.to_string(), &body, at(NOW + 61));
+
+        assert!(matches!(result, Err(WebhookError::TimestampOutOfTolerance)));
+    }
+
+    #[test]
+    fn test_replayed_delivery_is_rejected() {
+        let body = withdrawal_body();
+        let signature = sign(SECRET, NOW, &body);
+        let verifier = WebhookVerifier::new(SECRET).with_replay_protection();
+
+        verifier
+            .verify_at(&signature, &NOW.to_string(), &body, at(NOW))
+            .unwrap();
+        let result = verifier.verify_at(&signature, &NOW.to_string(), &body, at(NOW + 1));
+
+        assert!(matches!(result, Err(WebhookError::Replayed)));
+    }
+
+    #[test]
+    fn test_unknown_events_are_preserved() {
+        let event =
+            WebhookEvent::from_slice(br#"{"event":"invoice.expired","data":{"id":"i1"}}"#).unwrap();
+
+        assert_eq!(event.event_type(), "invoice.expired");
+        assert_eq!(event.object_id(), Some("i1"));
+    }
+
+    pub(crate) fn signed_headers(body: &[u8]) -> http::HeaderMap {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let mut headers = http::HeaderMap::new();
+        headers.insert(
+            SIGNATURE_HEADER,
+            sign(SECRET, timestamp, body).parse().unwrap(),
+        );
+        headers.insert(TIMESTAMP_HEADER, timestamp.to_string().parse().unwrap());
+        headers
+    }
+
+    #[tokio::test]
+    async fn test_receive_maps_outcomes_to_statuses() {
+        let verifier = WebhookVerifier::new(SECRET);
+        let body = withdrawal_body();
+        let headers = signed_headers(&body);
+        let ok = |_event: WebhookEvent| async { Ok::<_, String>(()) };
+        let failing = |_event: WebhookEvent| async { Err::<(), _>("database down".to_string()) };
+
+        assert_eq!(
+            receive(&verifier, &ok, &headers, &body).await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            receive(&verifier, &failing, &headers, &body).await,
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+        assert_eq!(
+            receive(&verifier, &ok, &http::HeaderMap::new(), &body).await,
+            StatusCode::UNAUTHORIZED
+        );
+    }
+
+    #[tokio::test]
+    async fn test_redelivery_after_handler_failure_is_handled() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        let verifier = WebhookVerifier::new(SECRET).with_replay_protection();
+        let body = withdrawal_body();
+        let headers = signed_headers(&body);
+        let calls = Arc::new(AtomicUsize::new(0));
+        let handler = {
+            let calls = calls.clone();
+            move |_event: WebhookEvent| {
+                // The first attempt fails, later ones succeed.
+                let call = calls.fetch_add(1, Ordering::SeqCst);
+                async move {
+                    match call {
+                        0 => Err("database down".to_string()),
+                        _ => Ok(()),
+                    }
+                }
+            }
+        };
+
+        assert_eq!(
+            receive(&verifier, &handler, &headers, &body).await,
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+        assert_eq!(
+            receive(&verifier, &handler, &headers, &body).await,
+            StatusCode::OK
+        );
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        // Once handled, further copies are acknowledged without handling.
+        assert_eq!(
+            receive(&verifier, &handler, &headers, &body).await,
+            StatusCode::OK
+        );
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,147 @@
+//! actix-web integration, enabled with the `actix-web` feature.
+//!
+//! Either mount a ready-made resource:
+//!
+//! ```ignore
+//! App::new().service(webhooks::actix::resource("/webhooks/bitvora", verifier, handle_event))
+//! ```
+//!
+//! or extract [`VerifiedWebhook`] in your own handler, with a
+//! `web::Data<WebhookVerifier>` registered as app data.
+
+use super::{receive, WebhookEvent, WebhookHandler, WebhookVerifier};
+use actix_web::dev::Payload;
+use actix_web::error::{ErrorInternalServerError, InternalError};
+use actix_web::http::StatusCode;
+use actix_web::{web, FromRequest, HttpRequest, HttpResponse, Resource};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+/// A resource at `path` that verifies each `POST`ed delivery and passes its
+/// event to `handler`.
+pub fn resource<H: WebhookHandler>(
+    path: &str,
+    verifier: Arc<WebhookVerifier>,
+    handler: H,
+) -> Resource {
+    web::resource(path).route(web::post().to(move |req: HttpRequest, body: web::Bytes| {
+        let verifier = verifier.clone();
+        let handler = handler.clone();
+        async move {
+            let status = receive(&verifier, &handler, &headers(&req), &body).await;
+            HttpResponse::new(status_code(status))
+        }
+    }))
+}
+
+/// Extractor for a verified webhook event.
+///
+/// Rejects forged or malformed deliveries with the status Bitvora expects,
+/// and acknowledges replayed ones without running the handler.
+/// If your handler then fails, call [`WebhookVerifier::forget`] with the
+/// delivery's signature so that its redelivery isn't acknowledged as a replay.
+#[derive(Debug, Clone)]
+pub struct VerifiedWebhook(pub WebhookEvent);
+
+impl FromRequest for VerifiedWebhook {
+    type Error = actix_web::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let verifier = req.app_data::<web::Data<WebhookVerifier>>().cloned();
+        let headers = headers(req);
+        let body = web::Bytes::from_request(req, payload);
+
+        Box::pin(async move {
+            let verifier = verifier.ok_or_else(|| {
+                ErrorInternalServerError("web::Data<WebhookVerifier> is not registered")
+            })?;
+            let body = body.await?;
+
+            verifier
+                .verify_headers(&headers, &body)
+                .map(VerifiedWebhook)
+                .map_err(|err| {
+                    let status = status_code(err.status());
+                    InternalError::new(err, status).into()
+                })
+        })
+    }
+}
+
+// actix-web is built on `http` 0.2, so headers and status codes are converted
+// to and from the `http` 1.x types the rest of the SDK uses.
+fn headers(req: &HttpRequest) -> http::HeaderMap {
+    let mut headers = http::HeaderMap::new();
+    for (name, value) in req.headers() {
+        if let (Ok(name), Ok(value)) = (
+            http::HeaderName::from_bytes(name.as_str().as_bytes()),
+            http::HeaderValue::from_bytes(value.as_bytes()),
+        ) {
+            headers.append(name, value);
+        }
+    }
+    headers
+}
+
+fn status_code(status: http::StatusCode) -> StatusCode {
+    StatusCode::from_u16(status.as_u16()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::webhooks::tests::{signed_headers, withdrawal_body, SECRET};
+    use actix_web::{test, App};
+
+    fn request(headers: &http::HeaderMap, body: Vec<u8>) -> test::TestRequest {
+        let mut request = test::TestRequest::post().uri("/webhooks");
+        for (name, value) in headers {
+            request = request.insert_header((name.as_str(), value.to_str().unwrap()));
+        }
+        request.set_payload(body)
+    }
+
+    #[actix_web::test]
+    async fn test_resource_verifies_and_handles() {
+        let verifier = Arc::new(WebhookVerifier::new(SECRET));
+        let app = test::init_service(App::new().service(resource(
+            "/webhooks",
+            verifier,
+            |_event: WebhookEvent| async { Err::<(), _>("not yet") },
+        )))
+        .await;
+        let body = withdrawal_body();
+
+        let response = test::call_service(
+            &app,
+            request(&signed_headers(&body), body.clone()).to_request(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+        let response =
+            test::call_service(&app, request(&http::HeaderMap::new(), body).to_request()).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_web::test]
+    async fn test_extractor_uses_verifier_from_app_data() {
+        async fn handler(VerifiedWebhook(event): VerifiedWebhook) -> HttpResponse {
+            HttpResponse::Ok().body(event.event_type().to_string())
+        }
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(WebhookVerifier::new(SECRET)))
+                .route("/webhooks", web::post().to(handler)),
+        )
+        .await;
+        let body = withdrawal_body();
+
+        let response =
+            test::call_service(&app, request(&signed_headers(&body), body).to_request()).await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
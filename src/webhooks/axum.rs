@@ -0,0 +1,115 @@
+//! axum integration, enabled with the `axum` feature.
+//!
+//! Either mount a ready-made route:
+//!
+//! ```ignore
+//! let app = Router::new().route("/webhooks/bitvora", webhooks::axum::route(verifier, handle_event));
+//! ```
+//!
+//! or extract [`VerifiedWebhook`] in your own handler, with an
+//! `Arc<WebhookVerifier>` reachable from the router state.
+
+use super::{receive, WebhookEvent, WebhookHandler, WebhookVerifier};
+use ::axum::body::Bytes;
+use ::axum::extract::{FromRef, FromRequest, Request};
+use ::axum::http::{HeaderMap, StatusCode};
+use ::axum::routing::{post, MethodRouter};
+use std::sync::Arc;
+
+/// A `POST` route that verifies each delivery and passes its event to
+/// `handler`.
+pub fn route<S, H>(verifier: Arc<WebhookVerifier>, handler: H) -> MethodRouter<S>
+where
+    S: Clone + Send + Sync + 'static,
+    H: WebhookHandler,
+{
+    post(move |headers: HeaderMap, body: Bytes| async move {
+        receive(&verifier, &handler, &headers, &body).await
+    })
+}
+
+/// Extractor for a verified webhook event.
+///
+/// Rejects forged or malformed deliveries with the status Bitvora expects,
+/// and acknowledges replayed ones without running the handler.
+/// If your handler then fails, call [`WebhookVerifier::forget`] with the
+/// delivery's signature so that its redelivery isn't acknowledged as a replay.
+#[derive(Debug, Clone)]
+pub struct VerifiedWebhook(pub WebhookEvent);
+
+impl<S> FromRequest<S> for VerifiedWebhook
+where
+    Arc<WebhookVerifier>: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = StatusCode;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let verifier = Arc::<WebhookVerifier>::from_ref(state);
+        let headers = req.headers().clone();
+        let body = Bytes::from_request(req, state)
+            .await
+            .map_err(|_| StatusCode::BAD_REQUEST)?;
+
+        verifier
+            .verify_headers(&headers, &body)
+            .map(VerifiedWebhook)
+            .map_err(|err| err.status())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::webhooks::tests::{signed_headers, withdrawal_body, SECRET};
+    use ::axum::body::Body;
+    use ::axum::Router;
+    use tower::ServiceExt;
+
+    fn request(headers: HeaderMap, body: Vec<u8>) -> Request {
+        let mut request = Request::post("/webhooks").body(Body::from(body)).unwrap();
+        *request.headers_mut() = headers;
+        request
+    }
+
+    #[tokio::test]
+    async fn test_route_verifies_and_handles() {
+        let verifier = Arc::new(WebhookVerifier::new(SECRET));
+        let app = Router::new().route(
+            "/webhooks",
+            route(verifier, |event: WebhookEvent| async move {
+                assert_eq!(event.event_type(), "withdrawal.completed");
+                Ok::<_, String>(())
+            }),
+        );
+        let body = withdrawal_body();
+
+        let response = app
+            .clone()
+            .oneshot(request(signed_headers(&body), body.clone()))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = app.oneshot(request(HeaderMap::new(), body)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_extractor_uses_verifier_from_state() {
+        async fn handler(VerifiedWebhook(event): VerifiedWebhook) -> String {
+            event.object_id().unwrap_or_default().to_string()
+        }
+        let app = Router::new()
+            .route("/webhooks", post(handler))
+            .with_state(Arc::new(WebhookVerifier::new(SECRET)));
+        let body = withdrawal_body();
+
+        let response = app
+            .oneshot(request(signed_headers(&body), body))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,128 @@
+//! hyper integration, enabled with the `hyper` feature.
+//!
+//! [`WebhookService`] is a `hyper::service::Service` that can be served with
+//! `hyper::server::conn` directly:
+//!
+//! ```ignore
+//! let service = WebhookService::new(verifier, handle_event);
+//! http1::Builder::new().serve_connection(io, service).await?;
+//! ```
+
+use super::{receive, WebhookHandler, WebhookVerifier};
+use bytes::Bytes;
+use http::{Method, Request, Response, StatusCode};
+use http_body_util::{BodyExt, Full, LengthLimitError, Limited};
+use hyper::body::Body;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use tower::BoxError;
+
+/// Largest delivery body the service reads, the same as actix-web's default
+/// payload limit. Larger bodies are answered with a 413.
+pub const MAX_BODY_SIZE: usize = 256 * 1024;
+
+/// Answers every `POST` by verifying the delivery and passing its event to the
+/// handler; other methods get a 405.
+#[derive(Clone)]
+pub struct WebhookService<H> {
+    verifier: Arc<WebhookVerifier>,
+    handler: H,
+}
+
+impl<H: WebhookHandler> WebhookService<H> {
+    pub fn new(verifier: Arc<WebhookVerifier>, handler: H) -> Self {
+        Self { verifier, handler }
+    }
+}
+
+impl<H, B> hyper::service::Service<Request<B>> for WebhookService<H>
+where
+    H: WebhookHandler,
+    B: Body + Send + 'static,
+    B::Data: Send,
+    B::Error: Into<BoxError>,
+{
+    type Response = Response<Full<Bytes>>;
+    type Error = std::convert::Infallible;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn call(&self, req: Request<B>) -> Self::Future {
+        let verifier = self.verifier.clone();
+        let handler = self.handler.clone();
+
+        Box::pin(async move {
+            if req.method() != Method::POST {
+                return Ok(empty(StatusCode::METHOD_NOT_ALLOWED));
+            }
+
+            let (parts, body) = req.into_parts();
+            let body = match Limited::new(body, MAX_BODY_SIZE).collect().await {
+                Ok(collected) => collected.to_bytes(),
+                Err(err) if err.is::<LengthLimitError>() => {
+                    return Ok(empty(StatusCode::PAYLOAD_TOO_LARGE))
+                }
+                Err(_) => return Ok(empty(StatusCode::BAD_REQUEST)),
+            };
+
+            let status = receive(&verifier, &handler, &parts.headers, &body).await;
+            Ok(empty(status))
+        })
+    }
+}
+
+fn empty(status: StatusCode) -> Response<Full<Bytes>> {
+    let mut response = Response::new(Full::new(Bytes::new()));
+    *response.status_mut() = status;
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::webhooks::tests::{signed_headers, withdrawal_body, SECRET};
+    use crate::webhooks::WebhookEvent;
+    use hyper::service::Service;
+
+    #[tokio::test]
+    async fn test_service_verifies_and_handles() {
+        let service = WebhookService::new(
+            Arc::new(WebhookVerifier::new(SECRET)),
+            |event: WebhookEvent| async move {
+                assert_eq!(event.object_id(), Some("w1"));
+                Ok::<_, String>(())
+            },
+        );
+        let body = withdrawal_body();
+
+        let mut request = Request::post("/webhooks")
+            .body(Full::new(Bytes::from(body.clone())))
+            .unwrap();
+        *request.headers_mut() = signed_headers(&body);
+        let response = service.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let request = Request::get("/webhooks")
+            .body(Full::new(Bytes::new()))
+            .unwrap();
+        let response = service.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+    }
+
+    #[tokio::test]
+    async fn test_oversized_bodies_are_rejected() {
+        let service = WebhookService::new(
+            Arc::new(WebhookVerifier::new(SECRET)),
+            |_: WebhookEvent| async move { Ok::<_, String>(()) },
+        );
+        let body = vec![b' '; MAX_BODY_SIZE + 1];
+
+        let mut request = Request::post("/webhooks")
+            .body(Full::new(Bytes::from(body.clone())))
+            .unwrap();
+        *request.headers_mut() = signed_headers(&body);
+        let response = service.call(request).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+}
@@ -0,0 +1,209 @@
+//! Signed sample deliveries for developing webhook handlers offline.
+//!
+//! The `bitvora-webhook-sim` binary (enabled with the `simulator` feature) is
+//! a command-line front end to this module.
+//!
+//! ```ignore
+//! let simulator = Simulator::new("http://localhost:3000/webhooks", webhook_secret);
+//! let body = sample_payload("withdrawal.completed", "w1");
+//! let status = simulator.deliver(&body, Fault::None).await?;
+//! ```
+
+use super::{sign, SIGNATURE_HEADER, TIMESTAMP_HEADER};
+use crate::errors::APIError;
+use crate::models::{GetDepositData, WithdrawData};
+use http::{HeaderMap, StatusCode};
+use reqwest::header::CONTENT_TYPE;
+use serde_json::json;
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+use zeroize::Zeroizing;
+
+/// Builds a delivery body for `event_type` about the object `id`.
+///
+/// Known event types carry a [`GetDepositData`] or [`WithdrawData`] with
+/// plausible values; other types get `{"id": id}` as their data.
+pub fn sample_payload(event_type: &str, id: &str) -> Vec<u8> {
+    let data = match event_type {
+        "deposit.lightning.completed" => deposit(id, "lightning", "completed"),
+        "deposit.lightning.failed" => deposit(id, "lightning", "failed"),
+        "deposit.onchain.pending" => deposit(id, "onchain", "pending"),
+        "deposit.onchain.completed" => deposit(id, "onchain", "completed"),
+        "withdrawal.pending" => withdrawal(id, "pending"),
+        "withdrawal.completed" => withdrawal(id, "completed"),
+        "withdrawal.failed" => withdrawal(id, "failed"),
+        _ => json!({ "id": id }),
+    };
+    serde_json::to_vec(&json!({ "event": event_type, "data": data }))
+        .expect("sample payloads serialize")
+}
+
+fn deposit(id: &str, rail_type: &str, status: &str) -> serde_json::Value {
+    let lightning = rail_type == "lightning";
+    serde_json::to_value(GetDepositData {
+        id: id.to_string(),
+        ledger_tx_id: format!("ledger_{}", id),
+        recipient: if lightning {
+            "simulator@bitvora.me".to_string()
+        } else {
+            "tb1qsimulatoraddress0000000000000000000000".to_string()
+        },
+        amount_sats: 21_000,
+        fee_sats: 0.0,
+        chain_tx_id: (!lightning).then(|| format!("{:0>64}", id)),
+        rail_type: rail_type.to_string(),
+        network_type: "signet".to_string(),
+        status: status.to_string(),
+        metadata: Some(HashMap::from([(
+            "source".to_string(),
+            "simulator".to_string(),
+        )])),
+        lightning_invoice_id: lightning.then(|| format!("invoice_{}", id)),
+        created_at: rfc3339_now(),
+    })
+    .expect("sample payloads serialize")
+}
+
+fn withdrawal(id: &str, status: &str) -> serde_json::Value {
+    serde_json::to_value(WithdrawData {
+        id: id.to_string(),
+        ledger_tx_id: Some(format!("ledger_{}", id)),
+        amount_sats: 21_000,
+        recipient: "simulator@bitvora.me".to_string(),
+        fee_sats: 1.0,
+        network_type: "signet".to_string(),
+        rail_type: "lightning".to_string(),
+        status: status.to_string(),
+        lightning_payment: None,
+        chain_tx_id: None,
+        metadata: Some(HashMap::from([(
+            "source".to_string(),
+            "simulator".to_string(),
+        )])),
+        created_at: rfc3339_now(),
+    })
+    .expect("sample payloads serialize")
+}
+
+fn rfc3339_now() -> String {
+    crate::debug::rfc3339(SystemTime::now())
+}
+
+/// A deliberate defect in a simulated delivery.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    None,
+    /// Signed with the wrong secret.
+    BadSignature,
+    /// Timestamped an hour ago, outside the default tolerance.
+    StaleTimestamp,
+}
+
+/// Signs sample deliveries and posts them to a local webhook URL.
+pub struct Simulator {
+    url: String,
+    secret: Zeroizing<Vec<u8>>,
+    client: reqwest::Client,
+}
+
+impl Simulator {
+    pub fn new(url: impl Into<String>, secret: impl AsRef<[u8]>) -> Self {
+        Self {
+            url: url.into(),
+            secret: Zeroizing::new(secret.as_ref().to_vec()),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// The signature and timestamp headers Bitvora would send for `body`.
+    pub fn headers(&self, body: &[u8], fault: Fault) -> HeaderMap {
+        let mut timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        if fault == Fault::StaleTimestamp {
+            timestamp -= 3600;
+        }
+        let signature = match fault {
+            Fault::BadSignature => sign(b"not-the-webhook-secret", timestamp, body),
+            Fault::None | Fault::StaleTimestamp => sign(&self.secret, timestamp, body),
+        };
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            SIGNATURE_HEADER,
+            signature.parse().expect("hex is a valid header value"),
+        );
+        headers.insert(
+            TIMESTAMP_HEADER,
+            timestamp
+                .to_string()
+                .parse()
+                .expect("digits are a valid header value"),
+        );
+        headers
+    }
+
+    /// Signs and posts `body`, returning the receiver's status.
+    pub async fn deliver(&self, body: &[u8], fault: Fault) -> Result<StatusCode, APIError> {
+        self.post(self.headers(body, fault), body).await
+    }
+
+    /// Posts `body` with the given headers, e.g. to resend an earlier delivery
+    /// unchanged.
+    pub async fn post(&self, headers: HeaderMap, body: &[u8]) -> Result<StatusCode, APIError> {
+        let response = self
+            .client
+            .post(&self.url)
+            .headers(headers)
+            .header(CONTENT_TYPE, "application/json")
+            .body(body.to_vec())
+            .send()
+            .await?;
+        Ok(response.status())
+    }
+}
+
+impl std::fmt::Debug for Simulator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Simulator")
+            .field("url", &self.url)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::webhooks::tests::SECRET;
+    use crate::webhooks::{WebhookError, WebhookEvent, WebhookVerifier, EVENT_TYPES};
+
+    #[test]
+    fn test_samples_parse_as_their_event_type() {
+        for event_type in EVENT_TYPES {
+            let event = WebhookEvent::from_slice(&sample_payload(event_type, "obj1")).unwrap();
+
+            assert!(!matches!(event, WebhookEvent::Unknown { .. }));
+            assert_eq!(event.event_type(), *event_type);
+            assert_eq!(event.object_id(), Some("obj1"));
+        }
+    }
+
+    #[test]
+    fn test_faults_are_rejected_by_verifier() {
+        let simulator = Simulator::new("http://localhost/webhooks", SECRET);
+        let verifier = WebhookVerifier::new(SECRET);
+        let body = sample_payload("withdrawal.completed", "w1");
+        let verify = |fault| verifier.verify_headers(&simulator.headers(&body, fault), &body);
+
+        assert!(verify(Fault::None).is_ok());
+        assert!(matches!(
+            verify(Fault::BadSignature),
+            Err(WebhookError::InvalidSignature)
+        ));
+        assert!(matches!(
+            verify(Fault::StaleTimestamp),
+            Err(WebhookError::TimestampOutOfTolerance)
+        ));
+    }
+}
@@ -0,0 +1,543 @@
+//! Durable webhook event log for deduplication and replay.
+//!
+//! Bitvora may deliver the same event more than once. Passing an
+//! [`EventStore`] to [`receive_once`] records every verified event with its
+//! raw payload and skips events that were already handled successfully, so
+//! handlers don't need to be idempotent. Events whose handler failed stay
+//! unhandled and are run again on redelivery, or with [`replay`].
+//!
+//! Before running the handler, [`receive_once`] claims the event in the store,
+//! so a redelivery that arrives while the first one is still being handled is
+//! answered with `409 Conflict` (and retried by Bitvora later) instead of
+//! running the handler a second time.
+//!
+//! ```ignore
+//! let store = SqliteEventStore::open("webhooks.db")?;
+//! let status = receive_once(&verifier, &store, &handler, &headers, &body).await;
+//!
+//! // After fixing a handler bug, re-run everything received since then.
+//! let report = replay(&store, &handler, since).await?;
+//! ```
+
+use super::{WebhookEvent, WebhookHandler, WebhookVerifier};
+use futures_util::future::BoxFuture;
+use http::StatusCode;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[cfg(feature = "sqlite")]
+mod sqlite;
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteEventStore;
+
+/// A received webhook event as recorded in an [`EventStore`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredEvent {
+    pub id: String,
+    pub event_type: String,
+    /// The delivery body exactly as received.
+    pub payload: Vec<u8>,
+    /// Unix seconds.
+    pub received_at: u64,
+    /// Unix seconds at which a handler last completed successfully.
+    pub handled_at: Option<u64>,
+    /// Unix seconds at which a delivery claimed the event, while its handler
+    /// runs.
+    pub claimed_at: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct EnvelopeId {
+    id: Option<String>,
+}
+
+impl StoredEvent {
+    /// Builds the record for a verified delivery.
+    ///
+    /// The id is the envelope's `id` when Bitvora sends one. Otherwise it is
+    /// the event type and object id (`"withdrawal.completed:<id>"`), which is
+    /// stable across redeliveries, falling back to a hash of the payload.
+    pub fn new(event: &WebhookEvent, payload: &[u8]) -> Self {
+        let id = serde_json::from_slice::<EnvelopeId>(payload)
+            .ok()
+            .and_then(|envelope| envelope.id)
+            .or_else(|| {
+                event
+                    .object_id()
+                    .map(|object_id| format!("{}:{}", event.event_type(), object_id))
+            })
+            .unwrap_or_else(|| format!("sha256:{}", hex::encode(Sha256::digest(payload))));
+
+        Self {
+            id,
+            event_type: event.event_type().to_string(),
+            payload: payload.to_vec(),
+            received_at: unix_now(),
+            handled_at: None,
+            claimed_at: None,
+        }
+    }
+
+    /// Parses the stored payload back into an event.
+    pub fn event(&self) -> Result<WebhookEvent, serde_json::Error> {
+        WebhookEvent::from_slice(&self.payload)
+    }
+}
+
+#[derive(Debug)]
+pub enum StoreError {
+    #[cfg(feature = "sqlite")]
+    Sqlite(rusqlite::Error),
+    Other(String),
+}
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(feature = "sqlite")]
+            StoreError::Sqlite(err) => write!(f, "SQLite event store error: {}", err),
+            StoreError::Other(message) => write!(f, "Event store error: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for StoreError {}
+
+/// How long a claim holds before it is treated as abandoned, for instance
+/// because the process handling the event crashed.
+pub const CLAIM_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// Outcome of [`EventStore::claim`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Claim {
+    /// The caller now holds the event and should run its handler.
+    Claimed,
+    /// Another delivery holds the event and is running its handler.
+    InProgress,
+    /// The event was already handled successfully.
+    Handled,
+}
+
+/// Storage for received webhook events.
+///
+/// Implement it to keep events in your own database. The methods return
+/// futures that run inside webhook handlers, so blocking I/O belongs in
+/// `tokio::task::spawn_blocking`. [`EventStore::claim`] must be atomic, for
+/// instance a compare-and-set under a lock or a conditional `UPDATE`, as it
+/// is what keeps concurrent deliveries of the same event from both running
+/// the handler.
+pub trait EventStore: Send + Sync {
+    /// Saves `event` unless an event with the same id is already stored.
+    /// Returns `true` if it was saved.
+    fn insert<'a>(&'a self, event: &'a StoredEvent) -> BoxFuture<'a, Result<bool, StoreError>>;
+
+    /// Claims a stored, unhandled event for handling at `now` (Unix seconds),
+    /// unless another claim younger than [`CLAIM_TIMEOUT`] holds it.
+    fn claim<'a>(&'a self, id: &'a str, now: u64) -> BoxFuture<'a, Result<Claim, StoreError>>;
+
+    /// Gives up the claim on an event whose handler failed, so that a later
+    /// delivery can claim it.
+    fn release<'a>(&'a self, id: &'a str) -> BoxFuture<'a, Result<(), StoreError>>;
+
+    /// Records that the event's handler completed successfully, releasing
+    /// its claim.
+    fn mark_handled<'a>(
+        &'a self,
+        id: &'a str,
+        handled_at: u64,
+    ) -> BoxFuture<'a, Result<(), StoreError>>;
+
+    fn get<'a>(&'a self, id: &'a str) -> BoxFuture<'a, Result<Option<StoredEvent>, StoreError>>;
+
+    /// Events received at or after `since` (Unix seconds), oldest first.
+    fn list(&self, since: u64) -> BoxFuture<'_, Result<Vec<StoredEvent>, StoreError>>;
+}
+
+/// Keeps events in memory; they are lost when the process exits.
+#[derive(Debug, Default)]
+pub struct MemoryEventStore {
+    // Events in insertion order, plus an index by id.
+    events: Mutex<(Vec<StoredEvent>, HashMap<String, usize>)>,
+}
+
+impl MemoryEventStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl EventStore for MemoryEventStore {
+    fn insert<'a>(&'a self, event: &'a StoredEvent) -> BoxFuture<'a, Result<bool, StoreError>> {
+        Box::pin(async move {
+            let mut guard = self.events.lock().unwrap();
+            let (events, index) = &mut *guard;
+            if index.contains_key(&event.id) {
+                return Ok(false);
+            }
+            index.insert(event.id.clone(), events.len());
+            events.push(event.clone());
+            Ok(true)
+        })
+    }
+
+    fn claim<'a>(&'a self, id: &'a str, now: u64) -> BoxFuture<'a, Result<Claim, StoreError>> {
+        Box::pin(async move {
+            let mut guard = self.events.lock().unwrap();
+            let (events, index) = &mut *guard;
+            let event = index
+                .get(id)
+                .map(|&position| &mut events[position])
+                .ok_or_else(|| StoreError::Other(format!("no stored event {}", id)))?;
+            if event.handled_at.is_some() {
+                return Ok(Claim::Handled);
+            }
+            if event
+                .claimed_at
+                .is_some_and(|claimed_at| !claim_expired(claimed_at, now))
+            {
+                return Ok(Claim::InProgress);
+            }
+            event.claimed_at = Some(now);
+            Ok(Claim::Claimed)
+        })
+    }
+
+    fn release<'a>(&'a self, id: &'a str) -> BoxFuture<'a, Result<(), StoreError>> {
+        Box::pin(async move {
+            let mut guard = self.events.lock().unwrap();
+            let (events, index) = &mut *guard;
+            if let Some(&position) = index.get(id) {
+                events[position].claimed_at = None;
+            }
+            Ok(())
+        })
+    }
+
+    fn mark_handled<'a>(
+        &'a self,
+        id: &'a str,
+        handled_at: u64,
+    ) -> BoxFuture<'a, Result<(), StoreError>> {
+        Box::pin(async move {
+            let mut guard = self.events.lock().unwrap();
+            let (events, index) = &mut *guard;
+            if let Some(&position) = index.get(id) {
+                events[position].handled_at = Some(handled_at);
+                events[position].claimed_at = None;
+            }
+            Ok(())
+        })
+    }
+
+    fn get<'a>(&'a self, id: &'a str) -> BoxFuture<'a, Result<Option<StoredEvent>, StoreError>> {
+        Box::pin(async move {
+            let guard = self.events.lock().unwrap();
+            let (events, index) = &*guard;
+            Ok(index.get(id).map(|&position| events[position].clone()))
+        })
+    }
+
+    fn list(&self, since: u64) -> BoxFuture<'_, Result<Vec<StoredEvent>, StoreError>> {
+        Box::pin(async move {
+            let guard = self.events.lock().unwrap();
+            let mut events: Vec<_> = guard
+                .0
+                .iter()
+                .filter(|event| event.received_at >= since)
+                .cloned()
+                .collect();
+            events.sort_by_key(|event| event.received_at);
+            Ok(events)
+        })
+    }
+}
+
+/// Like [`super::receive`], but records each event in `store` and skips
+/// events that were already handled successfully.
+///
+/// A delivery of an event that another delivery is still handling is
+/// answered with `409 Conflict`, so Bitvora retries it later.
+pub async fn receive_once<H: WebhookHandler>(
+    verifier: &WebhookVerifier,
+    store: &dyn EventStore,
+    handler: &H,
+    headers: &http::HeaderMap,
+    body: &[u8],
+) -> StatusCode {
+    let event = match verifier.verify_headers(headers, body) {
+        Ok(event) => event,
+        Err(err) => return err.status(),
+    };
+    let stored = StoredEvent::new(&event, body);
+
+    let claim = match store.insert(&stored).await {
+        Ok(_) => store.claim(&stored.id, unix_now()).await,
+        Err(err) => Err(err),
+    };
+    match claim {
+        Ok(Claim::Claimed) => {}
+        Ok(Claim::InProgress) => return StatusCode::CONFLICT,
+        Ok(Claim::Handled) => return StatusCode::OK,
+        Err(err) => {
+            log_failure(&stored.id, "failed to record webhook event", &err);
+            return StatusCode::INTERNAL_SERVER_ERROR;
+        }
+    }
+
+    if let Err(err) = handler.handle(event).await {
+        log_failure(&stored.id, "webhook handler failed", &err);
+        if let Err(err) = store.release(&stored.id).await {
+            log_failure(&stored.id, "failed to release webhook event", &err);
+        }
+        if let Some(signature) = headers
+            .get(super::SIGNATURE_HEADER)
+            .and_then(|value| value.to_str().ok())
+        {
+            verifier.forget(signature);
+        }
+        return StatusCode::INTERNAL_SERVER_ERROR;
+    }
+    if let Err(err) = store.mark_handled(&stored.id, unix_now()).await {
+        log_failure(&stored.id, "failed to mark webhook event handled", &err);
+    }
+    StatusCode::OK
+}
+
+/// Outcome of [`replay`].
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ReplayReport {
+    pub replayed: usize,
+    /// Ids of events whose payload no longer parses or whose handler failed.
+    pub failed: Vec<String>,
+}
+
+/// Runs `handler` again for every event received at or after `since` (Unix
+/// seconds), including ones already handled, oldest first.
+///
+/// Handler failures are logged and reported rather than stopping the replay.
+pub async fn replay<H: WebhookHandler>(
+    store: &dyn EventStore,
+    handler: &H,
+    since: u64,
+) -> Result<ReplayReport, StoreError> {
+    let mut report = ReplayReport::default();
+
+    for stored in store.list(since).await? {
+        let event = match stored.event() {
+            Ok(event) => event,
+            Err(err) => {
+                log_failure(&stored.id, "failed to parse stored webhook event", &err);
+                report.failed.push(stored.id);
+                continue;
+            }
+        };
+
+        match handler.handle(event).await {
+            Ok(()) => {
+                store.mark_handled(&stored.id, unix_now()).await?;
+                report.replayed += 1;
+            }
+            Err(err) => {
+                log_failure(&stored.id, "webhook handler failed", &err);
+                report.failed.push(stored.id);
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+fn claim_expired(claimed_at: u64, now: u64) -> bool {
+    now.saturating_sub(claimed_at) >= CLAIM_TIMEOUT.as_secs()
+}
+
+fn log_failure(event_id: &str, message: &str, err: &dyn fmt::Display) {
+    #[cfg(feature = "tracing")]
+    tracing::error!(event_id, error = %err, "{}", message);
+    #[cfg(not(feature = "tracing"))]
+    let _ = (event_id, message, err);
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::webhooks::tests::{signed_headers, withdrawal_body, SECRET};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// Checks the [`EventStore`] contract; shared with the SQLite tests.
+    pub(crate) async fn exercise_store(store: &dyn EventStore) {
+        let body = withdrawal_body();
+        let event = WebhookEvent::from_slice(&body).unwrap();
+        let mut stored = StoredEvent::new(&event, &body);
+        stored.received_at = 100;
+
+        assert!(store.insert(&stored).await.unwrap());
+        assert!(!store.insert(&stored).await.unwrap());
+        assert_eq!(
+            store.get("withdrawal.completed:w1").await.unwrap(),
+            Some(stored.clone())
+        );
+        assert_eq!(store.get("missing").await.unwrap(), None);
+
+        assert_eq!(store.claim(&stored.id, 110).await.unwrap(), Claim::Claimed);
+        assert_eq!(
+            store.get(&stored.id).await.unwrap().unwrap().claimed_at,
+            Some(110)
+        );
+        assert_eq!(
+            store.claim(&stored.id, 111).await.unwrap(),
+            Claim::InProgress
+        );
+        store.release(&stored.id).await.unwrap();
+        assert_eq!(store.claim(&stored.id, 120).await.unwrap(), Claim::Claimed);
+        // An abandoned claim can be taken over.
+        let expired = 120 + CLAIM_TIMEOUT.as_secs();
+        assert_eq!(
+            store.claim(&stored.id, expired).await.unwrap(),
+            Claim::Claimed
+        );
+        assert!(store.claim("missing", 120).await.is_err());
+
+        store.mark_handled(&stored.id, 150).await.unwrap();
+        let handled = store.get(&stored.id).await.unwrap().unwrap();
+        assert_eq!(handled.handled_at, Some(150));
+        assert_eq!(handled.claimed_at, None);
+        assert_eq!(store.claim(&stored.id, 160).await.unwrap(), Claim::Handled);
+
+        let mut later = stored.clone();
+        later.id = "later".to_string();
+        later.received_at = 200;
+        store.insert(&later).await.unwrap();
+
+        let ids = |events: Vec<StoredEvent>| -> Vec<String> {
+            events.into_iter().map(|e| e.id).collect()
+        };
+        assert_eq!(
+            ids(store.list(0).await.unwrap()),
+            vec!["withdrawal.completed:w1", "later"]
+        );
+        assert_eq!(ids(store.list(101).await.unwrap()), vec!["later"]);
+    }
+
+    #[tokio::test]
+    async fn test_memory_store() {
+        exercise_store(&MemoryEventStore::new()).await;
+    }
+
+    #[test]
+    fn test_envelope_id_takes_precedence() {
+        let body = br#"{"id":"evt_1","event":"invoice.expired","data":{}}"#;
+        let event = WebhookEvent::from_slice(body).unwrap();
+
+        assert_eq!(StoredEvent::new(&event, body).id, "evt_1");
+    }
+
+    fn counting_handler(
+        fail: bool,
+    ) -> (Arc<AtomicUsize>, impl WebhookHandler<Error = &'static str>) {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let handler = move |_event: WebhookEvent| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            async move {
+                if fail {
+                    Err("handler bug")
+                } else {
+                    Ok(())
+                }
+            }
+        };
+        (calls, handler)
+    }
+
+    #[tokio::test]
+    async fn test_receive_once_skips_handled_duplicates() {
+        let verifier = WebhookVerifier::new(SECRET);
+        let store = MemoryEventStore::new();
+        let body = withdrawal_body();
+        let (calls, handler) = counting_handler(false);
+
+        for _ in 0..2 {
+            let status =
+                receive_once(&verifier, &store, &handler, &signed_headers(&body), &body).await;
+            assert_eq!(status, StatusCode::OK);
+        }
+
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_deliveries_run_the_handler_once() {
+        let verifier = WebhookVerifier::new(SECRET);
+        let store = MemoryEventStore::new();
+        let body = withdrawal_body();
+        let calls = Arc::new(AtomicUsize::new(0));
+        let handler = {
+            let calls = calls.clone();
+            move |_event: WebhookEvent| {
+                calls.fetch_add(1, Ordering::SeqCst);
+                async {
+                    tokio::time::sleep(Duration::from_millis(20)).await;
+                    Ok::<_, String>(())
+                }
+            }
+        };
+
+        let headers = signed_headers(&body);
+        let (first, second) = tokio::join!(
+            receive_once(&verifier, &store, &handler, &headers, &body),
+            receive_once(&verifier, &store, &handler, &headers, &body),
+        );
+
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!([first, second], [StatusCode::OK, StatusCode::CONFLICT]);
+        // Bitvora's retry of the conflicting delivery is acknowledged.
+        let retried =
+            receive_once(&verifier, &store, &handler, &signed_headers(&body), &body).await;
+        assert_eq!(retried, StatusCode::OK);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_failed_events_are_retried_and_replayed() {
+        let verifier = WebhookVerifier::new(SECRET);
+        let store = MemoryEventStore::new();
+        let body = withdrawal_body();
+
+        let (_, broken) = counting_handler(true);
+        for _ in 0..2 {
+            let status =
+                receive_once(&verifier, &store, &broken, &signed_headers(&body), &body).await;
+            assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        }
+        let stored = store.get("withdrawal.completed:w1").await.unwrap().unwrap();
+        assert_eq!(stored.payload, body);
+        assert_eq!(stored.handled_at, None);
+
+        let (calls, fixed) = counting_handler(false);
+        let report = replay(&store, &fixed, 0).await.unwrap();
+
+        assert_eq!(report.replayed, 1);
+        assert!(report.failed.is_empty());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert!(store
+            .get("withdrawal.completed:w1")
+            .await
+            .unwrap()
+            .unwrap()
+            .handled_at
+            .is_some());
+    }
+}
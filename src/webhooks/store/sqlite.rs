@@ -0,0 +1,230 @@
+use super::{Claim, EventStore, StoreError, StoredEvent, CLAIM_TIMEOUT};
+use futures_util::future::BoxFuture;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// Keeps events in a SQLite database, enabled with the `sqlite` feature.
+///
+/// Queries run on tokio's blocking thread pool, so they don't stall the
+/// runtime that serves the webhooks.
+pub struct SqliteEventStore {
+    connection: Arc<Mutex<Connection>>,
+}
+
+impl SqliteEventStore {
+    /// Opens (or creates) the database at `path`.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, StoreError> {
+        Self::from_connection(Connection::open(path)?)
+    }
+
+    pub fn in_memory() -> Result<Self, StoreError> {
+        Self::from_connection(Connection::open_in_memory()?)
+    }
+
+    /// Uses an existing connection, creating the `bitvora_webhook_events`
+    /// table if needed.
+    pub fn from_connection(connection: Connection) -> Result<Self, StoreError> {
+        connection.execute_batch(
+            "CREATE TABLE IF NOT EXISTS bitvora_webhook_events (
+                id TEXT PRIMARY KEY,
+                event_type TEXT NOT NULL,
+                payload BLOB NOT NULL,
+                received_at INTEGER NOT NULL,
+                handled_at INTEGER,
+                claimed_at INTEGER
+            );
+            CREATE INDEX IF NOT EXISTS bitvora_webhook_events_received_at
+                ON bitvora_webhook_events (received_at);",
+        )?;
+        Ok(Self {
+            connection: Arc::new(Mutex::new(connection)),
+        })
+    }
+
+    /// Runs `query` with the connection on the blocking thread pool.
+    fn run<T, F>(&self, query: F) -> BoxFuture<'static, Result<T, StoreError>>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Connection) -> Result<T, StoreError> + Send + 'static,
+    {
+        let connection = self.connection.clone();
+        Box::pin(async move {
+            tokio::task::spawn_blocking(move || query(&connection.lock().unwrap()))
+                .await
+                .map_err(|err| StoreError::Other(err.to_string()))?
+        })
+    }
+}
+
+fn from_row(row: &Row<'_>) -> rusqlite::Result<StoredEvent> {
+    Ok(StoredEvent {
+        id: row.get(0)?,
+        event_type: row.get(1)?,
+        payload: row.get(2)?,
+        received_at: row.get::<_, i64>(3)? as u64,
+        handled_at: row.get::<_, Option<i64>>(4)?.map(|at| at as u64),
+        claimed_at: row.get::<_, Option<i64>>(5)?.map(|at| at as u64),
+    })
+}
+
+const COLUMNS: &str = "id, event_type, payload, received_at, handled_at, claimed_at";
+
+impl EventStore for SqliteEventStore {
+    fn insert<'a>(&'a self, event: &'a StoredEvent) -> BoxFuture<'a, Result<bool, StoreError>> {
+        let event = event.clone();
+        self.run(move |connection| {
+            let inserted = connection.execute(
+                "INSERT OR IGNORE INTO bitvora_webhook_events
+                    (id, event_type, payload, received_at, handled_at, claimed_at)
+                    VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    event.id,
+                    event.event_type,
+                    event.payload,
+                    event.received_at as i64,
+                    event.handled_at.map(|at| at as i64),
+                    event.claimed_at.map(|at| at as i64),
+                ],
+            )?;
+            Ok(inserted == 1)
+        })
+    }
+
+    fn claim<'a>(&'a self, id: &'a str, now: u64) -> BoxFuture<'a, Result<Claim, StoreError>> {
+        let id = id.to_string();
+        self.run(move |connection| {
+            // Conditional update, so that the check and the claim are one
+            // statement even when several processes share the database.
+            let claimed = connection.execute(
+                "UPDATE bitvora_webhook_events SET claimed_at = ?2
+                    WHERE id = ?1 AND handled_at IS NULL
+                        AND (claimed_at IS NULL OR claimed_at <= ?3)",
+                params![
+                    id,
+                    now as i64,
+                    now.saturating_sub(CLAIM_TIMEOUT.as_secs()) as i64
+                ],
+            )?;
+            if claimed == 1 {
+                return Ok(Claim::Claimed);
+            }
+
+            let handled: Option<Option<i64>> = connection
+                .query_row(
+                    "SELECT handled_at FROM bitvora_webhook_events WHERE id = ?1",
+                    params![id],
+                    |row| row.get(0),
+                )
+                .optional()?;
+            match handled {
+                Some(Some(_)) => Ok(Claim::Handled),
+                Some(None) => Ok(Claim::InProgress),
+                None => Err(StoreError::Other(format!("no stored event {}", id))),
+            }
+        })
+    }
+
+    fn release<'a>(&'a self, id: &'a str) -> BoxFuture<'a, Result<(), StoreError>> {
+        let id = id.to_string();
+        self.run(move |connection| {
+            connection.execute(
+                "UPDATE bitvora_webhook_events SET claimed_at = NULL WHERE id = ?1",
+                params![id],
+            )?;
+            Ok(())
+        })
+    }
+
+    fn mark_handled<'a>(
+        &'a self,
+        id: &'a str,
+        handled_at: u64,
+    ) -> BoxFuture<'a, Result<(), StoreError>> {
+        let id = id.to_string();
+        self.run(move |connection| {
+            connection.execute(
+                "UPDATE bitvora_webhook_events SET handled_at = ?2, claimed_at = NULL WHERE id = ?1",
+                params![id, handled_at as i64],
+            )?;
+            Ok(())
+        })
+    }
+
+    fn get<'a>(&'a self, id: &'a str) -> BoxFuture<'a, Result<Option<StoredEvent>, StoreError>> {
+        let id = id.to_string();
+        self.run(move |connection| {
+            let event = connection
+                .query_row(
+                    &format!(
+                        "SELECT {} FROM bitvora_webhook_events WHERE id = ?1",
+                        COLUMNS
+                    ),
+                    params![id],
+                    from_row,
+                )
+                .optional()?;
+            Ok(event)
+        })
+    }
+
+    fn list(&self, since: u64) -> BoxFuture<'_, Result<Vec<StoredEvent>, StoreError>> {
+        self.run(move |connection| {
+            let mut statement = connection.prepare(&format!(
+                "SELECT {} FROM bitvora_webhook_events WHERE received_at >= ?1
+                    ORDER BY received_at, rowid",
+                COLUMNS
+            ))?;
+            let events = statement
+                .query_map(params![since as i64], from_row)?
+                .collect::<Result<_, _>>()?;
+            Ok(events)
+        })
+    }
+}
+
+impl std::fmt::Debug for SqliteEventStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SqliteEventStore").finish_non_exhaustive()
+    }
+}
+
+impl From<rusqlite::Error> for StoreError {
+    fn from(err: rusqlite::Error) -> Self {
+        StoreError::Sqlite(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::webhooks::store::tests::exercise_store;
+
+    #[tokio::test]
+    async fn test_sqlite_store() {
+        exercise_store(&SqliteEventStore::in_memory().unwrap()).await;
+    }
+
+    #[tokio::test]
+    async fn test_events_survive_reopening() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("webhooks.db");
+        let event = StoredEvent {
+            id: "evt_1".to_string(),
+            event_type: "withdrawal.completed".to_string(),
+            payload: b"{}".to_vec(),
+            received_at: 100,
+            handled_at: None,
+            claimed_at: None,
+        };
+
+        SqliteEventStore::open(&path)
+            .unwrap()
+            .insert(&event)
+            .await
+            .unwrap();
+        let reopened = SqliteEventStore::open(&path).unwrap();
+
+        assert_eq!(reopened.get("evt_1").await.unwrap(), Some(event));
+    }
+}
@@ -0,0 +1,228 @@
+//! Tracking a withdrawal until it settles or fails.
+//!
+//! ```ignore
+//! let mut withdrawal = client.withdraw_and_track(request).await?;
+//! let settled = withdrawal.wait_until_final(Duration::from_secs(120)).await?;
+//! println!("{} {:?}", settled.status, settled.preimage());
+//! ```
+
+use crate::client::BitvoraClient;
+use crate::errors::WaitError;
+use crate::models::WithdrawData;
+use crate::wait::{wait_for, Backoff, Woken};
+use crate::webhooks::WebhookEvent;
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+/// Statuses after which a withdrawal no longer changes.
+pub const FINAL_STATUSES: &[&str] = &["completed", "settled", "succeeded", "failed"];
+
+impl WithdrawData {
+    pub fn is_final(&self) -> bool {
+        FINAL_STATUSES.contains(&self.status.as_str())
+    }
+
+    /// Preimage proving a lightning withdrawal was paid.
+    pub fn preimage(&self) -> Option<&str> {
+        self.lightning_payment
+            .as_ref()
+            .map(|payment| payment.payment_preimage.as_str())
+            .filter(|preimage| !preimage.is_empty())
+    }
+}
+
+/// Handle to a withdrawal that may not have reached a final status yet.
+#[derive(Debug)]
+pub struct PendingWithdrawal {
+    client: BitvoraClient,
+    id: String,
+    latest: Option<WithdrawData>,
+    backoff: Backoff,
+    events: Option<broadcast::Receiver<WebhookEvent>>,
+}
+
+impl PendingWithdrawal {
+    pub fn new(client: BitvoraClient, id: impl Into<String>) -> Self {
+        Self {
+            client,
+            id: id.into(),
+            latest: None,
+            backoff: Backoff::default(),
+            events: None,
+        }
+    }
+
+    pub(crate) fn from_data(client: BitvoraClient, data: WithdrawData) -> Self {
+        let mut pending = Self::new(client, data.id.clone());
+        pending.latest = Some(data);
+        pending
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// The most recent state fetched or received, if any.
+    pub fn latest(&self) -> Option<&WithdrawData> {
+        self.latest.as_ref()
+    }
+
+    /// Delay before the first re-check, doubling up to `max`. Defaults to 1s
+    /// and 30s.
+    pub fn with_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.backoff = Backoff { initial, max };
+        self
+    }
+
+    /// Also listens for webhook events, e.g. fed from a
+    /// [`crate::webhooks::WebhookHandler`], so the wait ends as soon as the
+    /// `withdrawal.completed` or `withdrawal.failed` event arrives instead of
+    /// at the next poll.
+    pub fn with_events(mut self, events: broadcast::Receiver<WebhookEvent>) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Resolves to the withdrawal once its status is final, whether it
+    /// settled or failed. On timeout the last state seen is returned in the
+    /// error, and the wait can be resumed by calling this again.
+    pub async fn wait_until_final(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<WithdrawData, WaitError<WithdrawData>> {
+        if let Some(latest) = self.latest.as_ref().filter(|data| data.is_final()) {
+            return Ok(latest.clone());
+        }
+
+        let client = &self.client;
+        let id = &self.id;
+        let result = wait_for(
+            timeout,
+            self.backoff,
+            || async move { Ok(client.get_withdrawal(id).await?.data) },
+            WithdrawData::is_final,
+            self.events.as_mut(),
+            |event| match event {
+                WebhookEvent::WithdrawalPending(data)
+                | WebhookEvent::WithdrawalCompleted(data)
+                | WebhookEvent::WithdrawalFailed(data)
+                    if data.id == *id =>
+                {
+                    Some(Woken::State(data))
+                }
+                _ => None,
+            },
+        )
+        .await;
+
+        match &result {
+            Ok(data) | Err(WaitError::Timeout(data)) => self.latest = Some(data.clone()),
+            Err(WaitError::Api(_)) | Err(WaitError::Expired(_)) => {}
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{self, with, Reply, Requests};
+    use serde_json::{json, Value};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    fn data(status: &str) -> Value {
+        let payment = if status == "completed" {
+            test_support::lightning_payment("beef")
+        } else {
+            Value::Null
+        };
+        with(
+            test_support::withdrawal("w1", status),
+            json!({"lightning_payment": payment}),
+        )
+    }
+
+    /// A client whose `get_withdrawal` answers with `statuses` in turn,
+    /// repeating the last one.
+    fn client(statuses: &'static [&'static str]) -> (BitvoraClient, Requests) {
+        let calls = Arc::new(AtomicUsize::new(0));
+        test_support::client(move |request| {
+            assert_eq!(request.path(), "/v1/transactions/withdrawals/w1");
+            let call = calls.fetch_add(1, Ordering::SeqCst);
+            Reply::ok(data(statuses[call.min(statuses.len() - 1)]))
+        })
+    }
+
+    #[tokio::test]
+    async fn test_polls_until_final() {
+        let (client, requests) = client(&["pending", "pending", "completed"]);
+        let mut withdrawal = client
+            .pending_withdrawal("w1")
+            .with_backoff(Duration::from_millis(1), Duration::from_millis(5));
+
+        let settled = withdrawal
+            .wait_until_final(Duration::from_secs(5))
+            .await
+            .unwrap();
+
+        assert_eq!(settled.status, "completed");
+        assert_eq!(settled.preimage(), Some("beef"));
+        assert_eq!(requests.len(), 3);
+        assert_eq!(withdrawal.latest().unwrap().status, "completed");
+    }
+
+    #[tokio::test]
+    async fn test_duration_max_waits_without_a_deadline() {
+        let (client, _) = client(&["pending", "completed"]);
+        let mut withdrawal = client
+            .pending_withdrawal("w1")
+            .with_backoff(Duration::from_millis(1), Duration::from_millis(1));
+
+        let settled = withdrawal.wait_until_final(Duration::MAX).await.unwrap();
+
+        assert_eq!(settled.status, "completed");
+    }
+
+    #[tokio::test]
+    async fn test_times_out_with_last_state() {
+        let (client, _) = client(&["pending"]);
+        let mut withdrawal = client
+            .pending_withdrawal("w1")
+            .with_backoff(Duration::from_millis(5), Duration::from_millis(5));
+
+        match withdrawal.wait_until_final(Duration::from_millis(20)).await {
+            Err(WaitError::Timeout(last)) => assert_eq!(last.status, "pending"),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_webhook_event_ends_wait_early() {
+        let (client, requests) = client(&["pending"]);
+        let (sender, receiver) = broadcast::channel(8);
+        let mut withdrawal = client
+            .pending_withdrawal("w1")
+            .with_backoff(Duration::from_secs(60), Duration::from_secs(60))
+            .with_events(receiver);
+
+        let event = WebhookEvent::from_slice(
+            json!({"event": "withdrawal.failed", "data": data("failed")})
+                .to_string()
+                .as_bytes(),
+        )
+        .unwrap();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            sender.send(event).unwrap();
+        });
+
+        let failed = withdrawal
+            .wait_until_final(Duration::from_secs(5))
+            .await
+            .unwrap();
+
+        assert_eq!(failed.status, "failed");
+        assert_eq!(requests.len(), 1);
+    }
+}